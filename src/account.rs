@@ -243,6 +243,35 @@ impl Account {
         Ok(())
     }
 
+    /// Sends a notice email with given subject and body to user.
+    ///
+    /// # Errors
+    ///
+    /// - Errors if the email send failed.
+    pub async fn send_notice<E>(
+        &self,
+        config: &config::SMTP,
+        transport: &AsyncSmtpTransport<E>,
+        subject: &str,
+        body: String,
+    ) -> Result<(), Error>
+    where
+        E: lettre::Executor,
+        AsyncSmtpTransport<E>: lettre::AsyncTransport<Error = smtp::Error>,
+    {
+        if crate::IS_TEST.load(std::sync::atomic::Ordering::Acquire) {
+            return Ok(());
+        }
+        send_email(
+            config,
+            transport,
+            self.inner.email().parse()?,
+            subject,
+            body,
+        )
+        .await
+    }
+
     /// Requests a verify session and sends an email to user.
     ///
     /// # Errors
@@ -295,6 +324,44 @@ impl Account {
     }
 }
 
+/// Sends an email with given subject and body to the address,
+/// from the configured SMTP sender.
+///
+/// # Errors
+///
+/// - Errors if the email send failed.
+async fn send_email<E>(
+    config: &config::SMTP,
+    transport: &AsyncSmtpTransport<E>,
+    to: lettre::Address,
+    subject: &str,
+    body: String,
+) -> Result<(), Error>
+where
+    E: lettre::Executor,
+    AsyncSmtpTransport<E>: lettre::AsyncTransport<Error = smtp::Error>,
+{
+    /// The sender name.
+    const SENDER: &str = "SubIT";
+
+    let msg = lettre::message::Message::builder()
+        .sender(lettre::message::Mailbox {
+            email: config.address.to_owned(),
+            name: Some(SENDER.to_owned()),
+        })
+        .to(lettre::message::Mailbox {
+            name: None,
+            email: to,
+        })
+        .subject(subject)
+        .body(body)?;
+    if let Err(err) = lettre::AsyncTransport::send(transport, msg).await {
+        tracing::error!("error sending email with smtp: {err}");
+        return Err(err.into());
+    }
+    Ok(())
+}
+
 impl From<libaccount::Account<Tag, Ext>> for Account {
     #[inline]
     fn from(inner: libaccount::Account<Tag, Ext>) -> Self {
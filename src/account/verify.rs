@@ -88,8 +88,6 @@ impl VerifyCx {
         E: lettre::Executor,
         AsyncSmtpTransport<E>: lettre::AsyncTransport<Error = smtp::Error>,
     {
-        /// The sender name.
-        const SENDER: &str = "SubIT";
         let captcha = self.update()?;
 
         if crate::IS_TEST.load(std::sync::atomic::Ordering::Acquire) {
@@ -97,25 +95,14 @@ impl VerifyCx {
             return Ok(());
         }
 
-        let msg = lettre::message::Message::builder()
-            .sender(lettre::message::Mailbox {
-                email: smtp_config.address.to_owned(),
-                name: Some(SENDER.to_owned()),
-            })
-            .to(lettre::message::Mailbox {
-                name: None,
-                email: to,
-            })
-            .subject("Your SubIT Screen Management System verification code")
-            .body(format!(
-                "Your verification code for {event} is: \n\n{captcha}",
-            ))?;
-        let result = lettre::AsyncTransport::send(transport, msg).await;
-        if let Err(err) = result {
-            tracing::error!("error sending email with smtp: {err}");
-            return Err(err.into());
-        }
-        Ok(())
+        super::send_email(
+            smtp_config,
+            transport,
+            to,
+            "Your SubIT Screen Management System verification code",
+            format!("Your verification code for {event} is: \n\n{captcha}"),
+        )
+        .await
     }
 
     /// Gets the captcha.
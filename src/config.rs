@@ -1,9 +1,15 @@
 //! The configuration of the server.
 
-use std::path::PathBuf;
+use std::{collections::HashMap, path::PathBuf};
 
 use lettre::{transport::smtp, AsyncSmtpTransport};
 use serde::{Deserialize, Serialize};
+use time::Duration;
+
+use crate::{
+    account::{Account, Tag},
+    resource::{store::StoreConfig, usage::UsageOwner, Variant},
+};
 
 /// The configuration of the server.
 #[derive(Debug, Serialize, Deserialize)]
@@ -18,9 +24,233 @@ pub struct Config {
 
     /// The root path of resource files.
     pub resource_path: PathBuf,
+    /// Storage backend of accepted resource files.
+    ///
+    /// Upload buffers are always stored in [`Self::resource_path`].
+    #[serde(default)]
+    pub resource_store: StoreConfig,
 
     /// Numbers of public screens.
     pub screens: usize,
+
+    /// Categories posts could be labeled with,
+    /// managed by admins.
+    ///
+    /// # Examples
+    ///
+    /// ```json
+    /// ["club", "academic", "sports", "lost & found"]
+    /// ```
+    #[serde(default)]
+    pub categories: Vec<String>,
+
+    /// Maximum on-screen durations of posts.
+    #[serde(default)]
+    pub post_max_dur: PostMaxDur,
+
+    /// Storage quotas of uploaded resources.
+    #[serde(default)]
+    pub quota: Quota,
+
+    /// Maximum sizes of uploaded resource payloads.
+    #[serde(default)]
+    pub payload_limit: PayloadLimit,
+}
+
+/// Maximum sizes of resource payloads of each variant, as bytes.
+///
+/// Variants without limits use the default one.
+///
+/// # Examples
+///
+/// ```json
+/// {
+///     "default": 52428800,
+///     "image": 10485760,
+///     "video": 524288000,
+/// }
+/// ```
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PayloadLimit {
+    /// The default limit.
+    #[serde(default = "PayloadLimit::default_limit")]
+    pub default: u64,
+    /// Limit of [`Variant::Image`].
+    #[serde(default)]
+    pub image: Option<u64>,
+    /// Limit of [`Variant::Pdf`].
+    #[serde(default)]
+    pub pdf: Option<u64>,
+    /// Limit of [`Variant::Video`].
+    #[serde(default)]
+    pub video: Option<u64>,
+    /// Limit of [`Variant::Html`].
+    #[serde(default)]
+    pub html: Option<u64>,
+}
+
+impl PayloadLimit {
+    /// The default limit if not configured, which is 50 MiB.
+    pub const DEFAULT_LIMIT: u64 = 50 * 1024 * 1024;
+
+    #[inline]
+    fn default_limit() -> u64 {
+        Self::DEFAULT_LIMIT
+    }
+
+    /// Gets the limit of payloads of the given variant.
+    ///
+    /// Variants without payloads are limited to `0`.
+    pub fn of(&self, variant: &Variant) -> u64 {
+        match variant {
+            Variant::Image { .. } => self.image,
+            Variant::Pdf { .. } => self.pdf,
+            Variant::Video { .. } => self.video,
+            Variant::Html { .. } => self.html,
+            Variant::Text { .. } => Some(0),
+        }
+        .unwrap_or(self.default)
+    }
+}
+
+impl Default for PayloadLimit {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            default: Self::DEFAULT_LIMIT,
+            image: None,
+            pdf: None,
+            video: None,
+            html: None,
+        }
+    }
+}
+
+/// Storage quotas of uploaded resources, as bytes.
+///
+/// Usages without quotas are unlimited.
+///
+/// # Examples
+///
+/// ```json
+/// {
+///     "account": 524288000,
+///     "departments": {
+///         "Office": 2147483648,
+///     },
+/// }
+/// ```
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Quota {
+    /// Quota of each account.
+    #[serde(default)]
+    pub account: Option<u64>,
+    /// Quotas shared by all accounts in each department.
+    #[serde(default)]
+    pub departments: HashMap<String, u64>,
+}
+
+impl Quota {
+    /// Gets the quota of the given usage owner.
+    ///
+    /// Returns `None` if the usage is unlimited.
+    pub fn of(&self, owner: &UsageOwner) -> Option<u64> {
+        match owner {
+            UsageOwner::Account(_) => self.account,
+            UsageOwner::Department(department) => self.departments.get(department).copied(),
+        }
+    }
+}
+
+/// Maximum on-screen durations of posts,
+/// with overrides for accounts with specific tags.
+///
+/// # Examples
+///
+/// ```json
+/// {
+///     "default": 7,
+///     "overrides": [
+///         {
+///             "entry": "Permission",
+///             "tag": "Maintain",
+///             "days": 31,
+///         },
+///         {
+///             "entry": "Department",
+///             "tag": "Office",
+///             "days": 31,
+///         },
+///     ],
+/// }
+/// ```
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PostMaxDur {
+    /// The default maximum duration, as days.
+    #[serde(default = "PostMaxDur::default_days")]
+    pub default: u32,
+    /// Overrides of the maximum duration.
+    ///
+    /// The longest duration among the default one and overrides
+    /// matching the tags of an account is the effective one.
+    #[serde(default)]
+    pub overrides: Vec<PostMaxDurOverride>,
+}
+
+/// An override of [`PostMaxDur`].
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PostMaxDurOverride {
+    /// The tag an account should contain.
+    #[serde(flatten)]
+    pub tag: Tag,
+    /// The maximum duration, as days.
+    pub days: u32,
+}
+
+impl PostMaxDur {
+    /// The default maximum duration as days,
+    /// which is [`Post::MAX_DUR`](crate::post::Post::MAX_DUR).
+    #[inline]
+    fn default_days() -> u32 {
+        crate::post::Post::MAX_DUR.whole_days() as u32
+    }
+
+    /// Gets the effective maximum post duration of the given account.
+    pub fn effective(&self, account: &Account) -> Duration {
+        let tags = account.tags();
+        let days = self
+            .overrides
+            .iter()
+            .filter(|o| match &o.tag {
+                Tag::Permission(_) => tags.contains_permission(&o.tag),
+                tag => tags
+                    .from_entry(&libaccount::tag::Tag::as_entry(tag))
+                    .is_some_and(|set| set.contains(tag)),
+            })
+            .map(|o| o.days)
+            .fold(self.default, u32::max);
+        Duration::days(days as i64)
+    }
+
+    /// Gets the longest maximum post duration among all accounts.
+    pub fn upper_bound(&self) -> Duration {
+        Duration::days(
+            self.overrides
+                .iter()
+                .map(|o| o.days)
+                .fold(self.default, u32::max) as i64,
+        )
+    }
+}
+
+impl Default for PostMaxDur {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            default: Self::default_days(),
+            overrides: vec![],
+        }
+    }
 }
 
 /// SMTP mailing configuration.
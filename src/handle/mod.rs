@@ -42,7 +42,102 @@ macro_rules! va {
         }
     }
 
+/// Sorting order of filtering results.
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Order {
+    /// Ascending order.
+    #[default]
+    Asc,
+    /// Descending order.
+    Desc,
+}
+
+/// Cursor of a sorted page, as the sorting key and id
+/// of the last item of the previous page.
+///
+/// The cursor is formatted as `<key>:<id>`.
+///
+/// # Examples
+///
+/// ```txt
+/// 2460311:1234567890
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cursor {
+    /// The sorting key.
+    pub key: i128,
+    /// The item id.
+    pub id: u64,
+}
+
+impl serde::Serialize for Cursor {
+    #[inline]
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(&format_args!("{}:{}", self.key, self.id))
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Cursor {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = <String as serde::Deserialize>::deserialize(deserializer)?;
+        value
+            .split_once(':')
+            .and_then(|(key, id)| {
+                Some(Self {
+                    key: key.parse().ok()?,
+                    id: id.parse().ok()?,
+                })
+            })
+            .ok_or_else(|| serde::de::Error::custom("invalid cursor"))
+    }
+}
+
+/// Sorts the given `(key, id)` pairs with given order, and keeps
+/// at most `limit` items right after the given cursor.
+///
+/// Pairs are compared by key first and then by id,
+/// so the order is stable between requests.
+///
+/// Returns the cursor of the next page, or `None`
+/// if there is no item left.
+///
+/// # Performance
+///
+/// Items are not stored in sorting order, so callers have to
+/// collect every matching item before sorting, and a sorted
+/// request scans all of them regardless of `limit`.
+pub fn sort_page(
+    items: &mut Vec<(i128, u64)>,
+    order: Order,
+    cursor: Option<Cursor>,
+    limit: usize,
+) -> Option<Cursor> {
+    if let Some(Cursor { key, id }) = cursor {
+        items.retain(|item| match order {
+            Order::Asc => *item > (key, id),
+            Order::Desc => *item < (key, id),
+        });
+    }
+    items.sort_unstable();
+    if order == Order::Desc {
+        items.reverse();
+    }
+    let more = items.len() > limit;
+    items.truncate(limit);
+    items
+        .last()
+        .filter(|_| more)
+        .map(|&(key, id)| Cursor { key, id })
+}
+
 pub mod account;
 pub mod notification;
 pub mod post;
 pub mod resource;
+pub mod stats;
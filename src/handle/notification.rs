@@ -13,7 +13,9 @@ use sms4_backend::{
 };
 use time::{Date, Duration, OffsetDateTime};
 
-use crate::{Auth, Error, Global};
+use crate::{Auth, Error, Global, Worlds};
+
+use super::{Cursor, Order};
 
 /// Request body for creating a new notification.
 ///
@@ -118,17 +120,72 @@ pub struct FilterNotificationParams {
     /// This only works with the permission [`Permission::ManageNotifications`].
     #[serde(default)]
     pub sender: Option<Id>,
+
+    /// Sort notifications with this key.\
+    /// The field can be omitted.
+    ///
+    /// If this field is present, `from` is ignored,
+    /// and pages are continued with `cursor`.
+    /// Sorting scans every matching record, so it
+    /// is slower than filtering without sorting.
+    #[serde(default)]
+    pub sort: Option<NotificationSort>,
+    /// Continue sorted notifications after this cursor,
+    /// as the `next` of the previous page.\
+    /// The field can be omitted.
+    #[serde(default)]
+    pub cursor: Option<Cursor>,
+    /// Sorting order.\
+    /// The field can be omitted,
+    /// and the default value is `Asc`.
+    #[serde(default)]
+    pub order: Order,
+
+    /// Whether to return information of the notifications inline.\
+    /// The field can be omitted,
+    /// and the default value is `false`.
+    ///
+    /// Information is selected in the same way as [`get_info`].
+    #[serde(default)]
+    pub expand: bool,
 }
 
 impl FilterNotificationParams {
     const DEFAULT_LIMIT: fn() -> usize = || 16;
 }
 
+/// Sorting key of notifications.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationSort {
+    /// Start time of the notification.
+    Time,
+}
+
+impl NotificationSort {
+    /// Gets the sorting key of given notification.
+    fn key(self, notification: &Notification) -> i128 {
+        match self {
+            NotificationSort::Time => notification.time().unix_timestamp_nanos(),
+        }
+    }
+}
+
 /// Response body for filtering notifications.
 #[derive(Serialize)]
 pub struct FilterNotificationRes {
     /// Notifications ids.
     pub notifications: Box<[Id]>,
+    /// Notification id => notification information.
+    ///
+    /// This field only presents if `expand` is requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub infos: Option<HashMap<u64, Info>>,
+    /// Cursor of the next page of sorted notifications.
+    ///
+    /// This field only presents if `sort` is requested
+    /// and there are more notifications.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next: Option<Cursor>,
 }
 
 /// Filters notifications.
@@ -139,6 +196,10 @@ pub async fn filter<Io: IoHandle>(
         from,
         limit,
         sender,
+        sort,
+        cursor,
+        order,
+        expand,
     }): Query<FilterNotificationParams>,
     auth: Auth,
     State(Global { worlds, .. }): State<Global<Io>>,
@@ -151,8 +212,11 @@ pub async fn filter<Io: IoHandle>(
         .tags()
         .contains_permission(&Tag::Permission(Permission::ManageNotifications));
 
+    // The id bound only works without sorting.
+    let from_bound = from.filter(|_| sort.is_none());
+
     let mut select = worlds.notification.select_all();
-    if let Some(from) = from {
+    if let Some(from) = from_bound {
         select = select.and(0, from.0..);
     }
     if let (Some(before), Some(after)) = (before, after) {
@@ -171,7 +235,7 @@ pub async fn filter<Io: IoHandle>(
     let mut notifications = Vec::new();
     let now = OffsetDateTime::now_utc();
     while let Some(Ok(lazy)) = iter.next().await {
-        if from.is_some_and(|a| lazy.id() <= a.0) {
+        if from_bound.is_some_and(|a| lazy.id() <= a.0) {
             continue;
         }
         if let Ok(val) = lazy.get().await {
@@ -182,14 +246,24 @@ pub async fn filter<Io: IoHandle>(
             {
                 continue;
             }
-            notifications.push(Id(val.id()));
-            if notifications.len() == limit {
+            notifications.push((sort.map_or(0, |s| s.key(val)), val.id()));
+            if sort.is_none() && notifications.len() == limit {
                 break;
             }
         }
     }
+
+    let next = sort.and_then(|_| super::sort_page(&mut notifications, order, cursor, limit));
+    let notifications: Box<[Id]> = notifications.into_iter().map(|(_, id)| Id(id)).collect();
+    let infos = if expand {
+        Some(bulk_infos(&worlds, &notifications, permitted_manage).await)
+    } else {
+        None
+    };
     Ok(Json(FilterNotificationRes {
-        notifications: notifications.into_boxed_slice(),
+        notifications,
+        infos,
+        next,
     }))
 }
 
@@ -219,6 +293,23 @@ impl Info {
             inner: notification.clone(),
         }
     }
+
+    /// Selects information of the notification viewed by an account
+    /// with given permission, or `None` if the notification is
+    /// invisible to the account.
+    fn for_viewer(
+        notification: &Notification,
+        permitted_manage: bool,
+        now: OffsetDateTime,
+    ) -> Option<Self> {
+        if permitted_manage {
+            Some(Self::from_full(notification))
+        } else if notification.time() <= now {
+            Some(Self::from_simple(notification))
+        } else {
+            None
+        }
+    }
 }
 
 /// Gets a notification.
@@ -237,14 +328,9 @@ pub async fn get_info<Io: IoHandle>(
     let select = sd!(worlds.notification, id);
     let lazy = gd!(select, id).ok_or(Error::NotificationNotFound(id))?;
     let notification = lazy.get().await?;
-    if notification.time() > OffsetDateTime::now_utc() && !permitted_manage {
-        return Err(Error::NotificationNotFound(id));
-    }
-    Ok(Json(if permitted_manage {
-        Info::from_full(notification)
-    } else {
-        Info::from_simple(notification)
-    }))
+    Info::for_viewer(notification, permitted_manage, OffsetDateTime::now_utc())
+        .map(Json)
+        .ok_or(Error::NotificationNotFound(id))
 }
 
 #[derive(Deserialize)]
@@ -265,8 +351,22 @@ pub async fn bulk_get_info<Io: IoHandle>(
         .tags()
         .contains_permission(&Tag::Permission(Permission::ManageNotifications));
 
+    Ok(Json(
+        bulk_infos(&worlds, &notifications, permitted_manage).await,
+    ))
+}
+
+/// Gets information of notifications with given ids, viewed by
+/// an account with given permission.
+///
+/// Notifications invisible to the account are omitted.
+async fn bulk_infos<Io: IoHandle>(
+    worlds: &Worlds<Io>,
+    notifications: &[Id],
+    permitted_manage: bool,
+) -> HashMap<u64, Info> {
     let Some(first) = notifications.first().copied() else {
-        return Ok(Json(HashMap::new()));
+        return HashMap::new();
     };
     let mut select = worlds
         .notification
@@ -281,18 +381,13 @@ pub async fn bulk_get_info<Io: IoHandle>(
     while let Some(Ok(lazy)) = iter.next().await {
         if notifications.contains(&Id(lazy.id())) {
             if let Ok(val) = lazy.get().await {
-                if val.time() <= now && !permitted_manage {
-                    continue;
-                }
-                if permitted_manage {
-                    res.insert(val.id(), Info::from_full(val));
-                } else {
-                    res.insert(val.id(), Info::from_simple(val));
+                if let Some(info) = Info::for_viewer(val, permitted_manage, now) {
+                    res.insert(val.id(), info);
                 }
             }
         }
     }
-    Ok(Json(res))
+    res
 }
 
 /// Removes a notification.
@@ -11,12 +11,16 @@ use dmds::{IoHandle, StreamExt};
 use serde::{Deserialize, Serialize};
 use sms4_backend::{
     account::{Permission, Tag},
+    config::Config,
     post::{Post, Priority, Status},
+    resource::store::ResourceStore,
     Error, Id,
 };
-use time::{Date, OffsetDateTime};
+use time::{Date, Duration, OffsetDateTime};
 
-use crate::{Auth, Global};
+use crate::{Auth, Global, Worlds};
+
+use super::{Cursor, Order};
 
 /// Request body for creating a new post.
 ///
@@ -33,6 +37,7 @@ use crate::{Auth, Global};
 ///     "resources": [1, 2, 3],
 ///     "grouped": true,
 ///     "priority": "Normal",
+///     "categories": ["club"],
 /// }
 /// ```
 #[derive(Deserialize)]
@@ -53,6 +58,12 @@ pub struct NewPostReq {
     pub grouped: bool,
     /// Priority of the post.
     pub priority: Priority,
+    /// Categories of the post.\
+    /// The field can be omitted.
+    ///
+    /// Each category should be one of [`Config::categories`].
+    #[serde(default)]
+    pub categories: Vec<String>,
 }
 
 /// Response body for creating a new post.
@@ -91,10 +102,12 @@ pub struct NewPostRes {
 /// - The given resources are not owned by the
 /// creator of this post, or there is no any
 /// resource in the given list.
-/// - The given time range is longer than **one week**.
+/// - The given time range is longer than the maximum post duration
+/// of the creator. See [`Config::post_max_dur`].
+/// - Any of the given categories is not configured.
 pub async fn new_post<Io: IoHandle>(
     auth: Auth,
-    State(Global { worlds, .. }): State<Global<Io>>,
+    State(Global { worlds, config, .. }): State<Global<Io>>,
     Json(NewPostReq {
         title,
         notes,
@@ -102,15 +115,35 @@ pub async fn new_post<Io: IoHandle>(
         resources,
         grouped,
         priority,
+        categories,
     }): Json<NewPostReq>,
 ) -> Result<Json<NewPostRes>, Error> {
     let select = sd!(worlds.account, auth.account);
-    va!(auth, select => Post);
+    let this_lazy = va!(auth, select => Post);
+    let max_dur = config.post_max_dur.effective(this_lazy.get().await?);
+    validate_categories(&config, &categories)?;
 
-    let mut validated = 0;
+    let first = resources.first().ok_or(Error::PostResourceEmpty)?.0;
+    let mut post = Post::new(
+        title,
+        notes,
+        time,
+        resources,
+        auth.account,
+        grouped,
+        priority,
+        max_dur,
+    )?;
+    post.set_categories(categories);
+    let id = post.id();
+    let resources = post.resources();
+
+    // All resources are validated before any of them is blocked,
+    // so no resource is left blocked by a rejected post.
+    let mut validated = vec![];
     let mut select = worlds
         .resource
-        .select(0, resources.first().ok_or(Error::PostResourceEmpty)?.0)
+        .select(0, first)
         .hints(resources.iter().copied().map(From::from));
     for id in resources.iter().copied() {
         select = select.plus(0, id.0)
@@ -118,34 +151,23 @@ pub async fn new_post<Io: IoHandle>(
     let mut iter = select.iter();
     while let Some(Ok(mut lazy)) = iter.next().await {
         if resources.contains(&Id(lazy.id())) {
-            if let Ok(val) = lazy.get().await {
+            if let Ok(val) = lazy.get_mut().await {
                 if val.owner() == Id(auth.account) {
-                    if let Ok(val) = lazy.get_mut().await {
-                        val.block()?;
-                        lazy.close().await?;
-                        validated += 1;
-                    }
+                    validated.push(lazy);
                 }
             }
         }
-        if validated >= resources.len() {
+        if validated.len() >= resources.len() {
             break;
         }
     }
-    if validated < resources.len() {
+    if validated.len() < resources.len() {
         return Err(Error::PermissionDenied);
     }
-
-    let post = Post::new(
-        title,
-        notes,
-        time,
-        resources,
-        auth.account,
-        grouped,
-        priority,
-    )?;
-    let id = post.id();
+    for mut lazy in validated {
+        lazy.get_mut().await?.block(id);
+        lazy.close().await?;
+    }
 
     worlds
         .post
@@ -156,6 +178,15 @@ pub async fn new_post<Io: IoHandle>(
     Ok(Json(NewPostRes { id: id.into() }))
 }
 
+/// Validates that all the given categories are configured.
+fn validate_categories(config: &Config, categories: &[String]) -> Result<(), Error> {
+    if let Some(c) = categories.iter().find(|c| !config.categories.contains(*c)) {
+        Err(Error::PostCategoryNotFound(c.to_owned()))
+    } else {
+        Ok(())
+    }
+}
+
 /// Request URL query parameters for filtering posts.
 ///
 /// # Examples
@@ -196,12 +227,83 @@ pub struct FilterPostsParams {
     /// The field can be omitted.
     #[serde(default)]
     pub screen: Option<usize>,
+
+    /// Filter with post category.\
+    /// The field can be omitted.
+    #[serde(default)]
+    pub category: Option<String>,
+
+    /// Filter posts using this resource.\
+    /// The field can be omitted.
+    ///
+    /// No post is returned if the resource does not exist.
+    #[serde(default)]
+    pub resource: Option<Id>,
+
+    /// Sort posts with this key.\
+    /// The field can be omitted.
+    ///
+    /// If this field is present, `from` is ignored,
+    /// and pages are continued with `cursor`.
+    /// Sorting scans every matching record, so it
+    /// is slower than filtering without sorting.
+    #[serde(default)]
+    pub sort: Option<PostSort>,
+    /// Continue sorted posts after this cursor,
+    /// as the `next` of the previous page.\
+    /// The field can be omitted.
+    #[serde(default)]
+    pub cursor: Option<Cursor>,
+    /// Sorting order.\
+    /// The field can be omitted,
+    /// and the default value is `Asc`.
+    #[serde(default)]
+    pub order: Order,
+
+    /// Whether to return information of the posts inline.\
+    /// The field can be omitted,
+    /// and the default value is `false`.
+    ///
+    /// Full information is returned for posts created by the
+    /// authorized account, or if the account has the permission
+    /// [`Permission::ReviewPost`]. Otherwise, simple information
+    /// is returned.
+    #[serde(default)]
+    pub expand: bool,
 }
 
 impl FilterPostsParams {
     const DEFAULT_LIMIT: fn() -> usize = || 64;
 }
 
+/// Sorting key of posts.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PostSort {
+    /// Start date of the post.
+    Start,
+    /// End date of the post.
+    End,
+    /// Priority of the post.
+    Priority,
+    /// Time of the last state of the post.
+    LastState,
+    /// Creation time of the post.
+    Creation,
+}
+
+impl PostSort {
+    /// Gets the sorting key of given post.
+    fn key(self, post: &Post) -> i128 {
+        match self {
+            PostSort::Start => post.time().start().to_julian_day() as i128,
+            PostSort::End => post.time().end().to_julian_day() as i128,
+            PostSort::Priority => post.priority() as u8 as i128,
+            PostSort::LastState => post.state().time().unix_timestamp_nanos(),
+            PostSort::Creation => post.states()[0].time().unix_timestamp_nanos(),
+        }
+    }
+}
+
 /// Response body for filtering posts.
 ///
 /// # Examples
@@ -215,6 +317,17 @@ impl FilterPostsParams {
 pub struct FilterPostsRes {
     /// List of post ids.
     pub posts: Box<[Id]>,
+    /// Post id => post information.
+    ///
+    /// This field only presents if `expand` is requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub infos: Option<HashMap<u64, Info>>,
+    /// Cursor of the next page of sorted posts.
+    ///
+    /// This field only presents if `sort` is requested
+    /// and there are more posts.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next: Option<Cursor>,
 }
 
 /// Filters posts with given filter options.
@@ -234,6 +347,12 @@ pub async fn filter<Io: IoHandle>(
         status,
         on,
         screen,
+        category,
+        resource,
+        sort,
+        cursor,
+        order,
+        expand,
     }): Query<FilterPostsParams>,
     auth: Auth,
     State(Global { worlds, config, .. }): State<Global<Io>>,
@@ -251,13 +370,34 @@ pub async fn filter<Io: IoHandle>(
         .tags()
         .contains_permission(&Tag::Permission(Permission::GetPubPost));
 
+    let resource_posts = if let Some(Id(resource)) = resource {
+        let select = sd!(worlds.resource, resource);
+        let Some(lazy) = gd!(select, resource) else {
+            // No post could use a resource that does not exist.
+            return Ok(Json(FilterPostsRes {
+                posts: Box::new([]),
+                infos: expand.then(HashMap::new),
+                next: None,
+            }));
+        };
+        Some(lazy.get().await?.posts().to_owned())
+    } else {
+        None
+    };
+
+    // The id bound only works without sorting.
+    let from_bound = from.filter(|_| sort.is_none());
+
     let mut select = worlds.post.select_all();
-    if let Some(from) = from {
+    if let Some(from) = from_bound {
         select = select.and(0, from.0..);
     }
     if let Some(creator) = creator {
         select = select.and(2, creator.0);
     }
+    if let Some(ref posts) = resource_posts {
+        select = select.hints(posts.iter().copied());
+    }
     if let Some(status) = status {
         if matches!(status, sms4_backend::post::Status::Approved) {
             select = select.and(3, 1);
@@ -265,9 +405,10 @@ pub async fn filter<Io: IoHandle>(
             select = select.and(3, 0);
         }
     }
-    if let Some(on) = on {
-        let end_o = (on + Post::MAX_DUR).ordinal();
-        let start_o = (on - Post::MAX_DUR).ordinal();
+    let max_dur = config.post_max_dur.upper_bound();
+    if let Some(on) = on.filter(|_| max_dur * 2 < Duration::days(365)) {
+        let end_o = (on + max_dur).ordinal();
+        let start_o = (on - max_dur).ordinal();
         if start_o > end_o {
             select = select.and(1, start_o as u64..).plus(1, ..=end_o as u64);
         } else {
@@ -278,8 +419,11 @@ pub async fn filter<Io: IoHandle>(
     let mut iter = select.iter();
     let mut posts = Vec::new();
     while let Some(Ok(lazy)) = iter.next().await {
-        if from.is_some_and(|a| lazy.id() <= a.0)
+        if from_bound.is_some_and(|a| lazy.id() <= a.0)
             || screen.is_some_and(|s| lazy.id() % config.screens as u64 != s as u64)
+            || resource_posts
+                .as_ref()
+                .is_some_and(|p| !p.contains(&lazy.id()))
         {
             continue;
         }
@@ -287,6 +431,9 @@ pub async fn filter<Io: IoHandle>(
             if creator.is_some_and(|c| val.creator() != c)
                 || status.is_some_and(|s| val.state().status() != s)
                 || on.is_some_and(|d| !val.time().contains(&d))
+                || category
+                    .as_ref()
+                    .is_some_and(|c| !val.categories().contains(c))
                 || (val.creator() != Id(auth.account)
                     && !if matches!(val.state().status(), sms4_backend::post::Status::Approved) {
                         permitted_get_pub
@@ -296,15 +443,26 @@ pub async fn filter<Io: IoHandle>(
             {
                 continue;
             }
-            posts.push(Id(val.id()));
-            if posts.len() == limit {
+            posts.push((sort.map_or(0, |s| s.key(val)), val.id()));
+            if sort.is_none() && posts.len() == limit {
                 break;
             }
         }
     }
-    Ok(Json(FilterPostsRes {
-        posts: posts.into_boxed_slice(),
-    }))
+
+    let next = sort.and_then(|_| super::sort_page(&mut posts, order, cursor, limit));
+    let posts: Box<[Id]> = posts.into_iter().map(|(_, id)| Id(id)).collect();
+    let infos = if expand {
+        Some(
+            bulk_infos(&worlds, &posts, |post| {
+                Some(Info::for_listed(post, Id(auth.account), permitted_review))
+            })
+            .await,
+        )
+    } else {
+        None
+    };
+    Ok(Json(FilterPostsRes { posts, infos, next }))
 }
 
 /// Represents information of a post.
@@ -319,11 +477,16 @@ pub enum Info {
         creator: Id,
         /// List of resource ids this post used.
         resources: Box<[Id]>,
+        /// Resources of this post found damaged
+        /// by storage scrubbing.
+        damaged_resources: Vec<Id>,
         /// Whether this post should be played as
         /// a full sequence.
         grouped: bool,
         /// Priority of the post.
         priority: Priority,
+        /// Categories of the post.
+        categories: Vec<String>,
     },
 
     /// Full information of a post.
@@ -351,8 +514,10 @@ impl Info {
             title: post.title().to_owned(),
             creator: post.creator(),
             resources: post.resources().to_owned().into_boxed_slice(),
+            damaged_resources: post.damaged_resources().to_vec(),
             grouped: post.is_grouped(),
             priority: post.priority(),
+            categories: post.categories().to_owned(),
         }
     }
 
@@ -362,6 +527,39 @@ impl Info {
             inner: post.clone(),
         }
     }
+
+    /// Selects information of the post viewed by the given account
+    /// with given permissions, or `None` if the post is invisible
+    /// to the account.
+    fn for_viewer(
+        post: &Post,
+        account: Id,
+        permitted_review: bool,
+        permitted_get_pub: bool,
+        today: Date,
+    ) -> Option<Self> {
+        if post.creator() == account || permitted_review {
+            Some(Self::from_full(post))
+        } else if permitted_get_pub
+            && matches!(post.state().status(), Status::Approved)
+            && post.time().contains(&today)
+        {
+            Some(Self::from_simple(post))
+        } else {
+            None
+        }
+    }
+
+    /// Selects information of a post listed by [`filter`] for the
+    /// given account, regardless of the day it's available on.
+    #[inline]
+    fn for_listed(post: &Post, account: Id, permitted_review: bool) -> Self {
+        if post.creator() == account || permitted_review {
+            Self::from_full(post)
+        } else {
+            Self::from_simple(post)
+        }
+    }
 }
 
 pub async fn get_info<Io: IoHandle>(
@@ -386,16 +584,15 @@ pub async fn get_info<Io: IoHandle>(
     let lazy = gd!(select, id).ok_or(Error::PostNotFound(id))?;
     let val = lazy.get().await?;
 
-    if val.creator() == Id(auth.account) || permitted_review {
-        Ok(Json(Info::from_full(val)))
-    } else if permitted_get_pub
-        && matches!(val.state().status(), sms4_backend::post::Status::Approved)
-        && val.time().contains(&now)
-    {
-        Ok(Json(Info::from_simple(val)))
-    } else {
-        Err(Error::PostNotFound(id))
-    }
+    Info::for_viewer(
+        val,
+        Id(auth.account),
+        permitted_review,
+        permitted_get_pub,
+        now,
+    )
+    .map(Json)
+    .ok_or(Error::PostNotFound(id))
 }
 
 #[derive(Deserialize)]
@@ -426,8 +623,31 @@ pub async fn bulk_get_info<Io: IoHandle>(
         .tags()
         .contains_permission(&Tag::Permission(Permission::GetPubPost));
 
+    let now = OffsetDateTime::now_utc().date();
+    Ok(Json(
+        bulk_infos(&worlds, &posts, |post| {
+            Info::for_viewer(
+                post,
+                Id(auth.account),
+                permitted_review,
+                permitted_get_pub,
+                now,
+            )
+        })
+        .await,
+    ))
+}
+
+/// Gets information of posts with given ids, selected by `select`.
+///
+/// Posts selected as `None` are omitted.
+async fn bulk_infos<Io: IoHandle>(
+    worlds: &Worlds<Io>,
+    posts: &[Id],
+    select: impl Fn(&Post) -> Option<Info>,
+) -> HashMap<u64, Info> {
     let Some(first) = posts.first().copied() else {
-        return Ok(Json(HashMap::new()));
+        return HashMap::new();
     };
     let mut select = worlds
         .post
@@ -438,22 +658,16 @@ pub async fn bulk_get_info<Io: IoHandle>(
     }
     let mut iter = select.iter();
     let mut res = HashMap::with_capacity(posts.len().max(64));
-    let now = OffsetDateTime::now_utc().date();
     while let Some(Ok(lazy)) = iter.next().await {
         if posts.contains(&Id(lazy.id())) {
             if let Ok(val) = lazy.get().await {
-                if val.creator() == Id(auth.account) || permitted_review {
-                    res.insert(val.id(), Info::from_full(val));
-                } else if permitted_get_pub
-                    && matches!(val.state().status(), sms4_backend::post::Status::Approved)
-                    && val.time().contains(&now)
-                {
-                    res.insert(val.id(), Info::from_simple(val));
+                if let Some(info) = select(val) {
+                    res.insert(val.id(), info);
                 }
             }
         }
     }
-    Ok(Json(res))
+    res
 }
 
 #[derive(Deserialize)]
@@ -475,16 +689,29 @@ pub struct ModifyReq {
 
     #[serde(default)]
     pub grouped: Option<bool>,
+
+    /// Overrides the categories of the post.
+    #[serde(default)]
+    pub categories: Option<Vec<String>>,
 }
 
 pub async fn modify<Io: IoHandle>(
     Path(id): Path<Id>,
     auth: Auth,
-    State(Global { worlds, .. }): State<Global<Io>>,
+    State(Global {
+        worlds,
+        config,
+        resource_store,
+        resource_locks,
+        ..
+    }): State<Global<Io>>,
     Json(mut req): Json<ModifyReq>,
 ) -> Result<(), Error> {
     let select = sd!(worlds.account, auth.account);
     va!(auth, select => Post);
+    if let Some(categories) = &req.categories {
+        validate_categories(&config, categories)?;
+    }
     let select = sd!(worlds.post, id.0);
     let mut lazy = gd!(select, id.0).ok_or(Error::PostNotFound(id.0))?;
     let post = lazy.get_mut().await?;
@@ -492,17 +719,8 @@ pub async fn modify<Io: IoHandle>(
         return Err(Error::PostNotFound(id.0));
     }
 
-    macro_rules! modify {
-        ($($i:ident => $m:ident),*$(,)?) => { $(if let Some(v) = req.$i.take() { post.$m(v) })* };
-    }
-    modify! {
-        title => set_title,
-        grouped => set_is_grouped,
-    }
-    if let Some(time) = req.time.take() {
-        post.set_time(time)?
-    }
-    if let Some(new_res) = req
+    // New resources are validated before the post is modified.
+    let linked = if let Some(new_res) = req
         .resources
         .take()
         .map(|s| s.iter().copied().collect::<HashSet<_>>())
@@ -516,39 +734,62 @@ pub async fn modify<Io: IoHandle>(
             .difference(&new_res)
             .copied()
             .collect::<HashSet<_>>();
-        let mut result = new_res.intersection(&old_res).copied().collect::<Vec<_>>();
+        let result = new_res.intersection(&old_res).copied().collect::<Vec<_>>();
 
-        if !(new_diff.is_empty() && old_diff.is_empty()) {
+        let mut validated = vec![];
+        if let Some(first) = new_diff.iter().copied().next() {
             let mut select = worlds
                 .resource
-                .select(
-                    0,
-                    new_diff
-                        .iter()
-                        .copied()
-                        .next()
-                        .map_or_else(|| old_diff.iter().copied().next().unwrap().0, |i| i.0),
-                )
-                .hints(new_diff.iter().copied().map(From::from))
-                .hints(old_diff.iter().copied().map(From::from));
-            for id in old_diff.iter().copied().chain(new_diff.iter().copied()) {
+                .select(0, first.0)
+                .hints(new_diff.iter().copied().map(From::from));
+            for id in new_diff.iter().copied() {
                 select = select.plus(0, id.0)
             }
             let mut iter = select.iter();
-
             while let Some(Ok(mut lazy)) = iter.next().await {
-                if old_diff.contains(&Id(lazy.id())) {
-                    lazy.destroy().await?;
-                } else if new_diff.contains(&Id(lazy.id())) {
-                    let res = lazy.get_mut().await?;
-                    res.block()?;
-                    result.push(Id(lazy.id()));
-                    lazy.close().await?;
-                } else {
-                    continue;
+                if new_diff.contains(&Id(lazy.id()))
+                    && lazy.get_mut().await?.owner() == Id(auth.account)
+                {
+                    validated.push(lazy);
                 }
             }
         }
+        if validated.len() < new_diff.len() {
+            return Err(Error::PermissionDenied);
+        }
+        Some((validated, old_diff, result))
+    } else {
+        None
+    };
+
+    macro_rules! modify {
+        ($($i:ident => $m:ident),*$(,)?) => { $(if let Some(v) = req.$i.take() { post.$m(v) })* };
+    }
+    modify! {
+        title => set_title,
+        grouped => set_is_grouped,
+        categories => set_categories,
+    }
+    if let Some(time) = req.time.take() {
+        // The time range is limited by the maximum duration
+        // of the creator, rather than the modifying account.
+        let select = sd!(worlds.account, post.creator().0);
+        let creator = gd!(select, post.creator().0).ok_or(Error::AccountNotFound)?;
+        post.set_time(time, config.post_max_dur.effective(creator.get().await?))?
+    }
+    if let Some((validated, old_diff, mut result)) = linked {
+        for mut lazy in validated {
+            lazy.get_mut().await?.block(id.0);
+            result.push(Id(lazy.id()));
+            lazy.close().await?;
+        }
+        unlink_resources(
+            &worlds,
+            &*resource_store,
+            &resource_locks,
+            old_diff.into_iter().map(|res| (res, vec![id.0])).collect(),
+        )
+        .await?;
 
         post.set_resources(result.into_boxed_slice());
     }
@@ -576,6 +817,17 @@ pub async fn review<Io: IoHandle>(
 ) -> Result<(), Error> {
     let select = sd!(worlds.account, auth.account);
     va!(auth, select => ReviewPost);
+    review_post(&worlds, id, status, message, auth.account).await
+}
+
+/// Pushes a review result state into the post with given id.
+async fn review_post<Io: IoHandle>(
+    worlds: &Worlds<Io>,
+    id: Id,
+    status: Status,
+    message: Option<String>,
+    operator: u64,
+) -> Result<(), Error> {
     if !matches!(status, Status::Approved | Status::Rejected) {
         return Err(Error::InvalidPostStatus);
     }
@@ -584,16 +836,134 @@ pub async fn review<Io: IoHandle>(
     let post = lazy.get_mut().await?;
     post.pust_state(sms4_backend::post::State::new(
         status,
-        auth.account,
+        operator,
         message.unwrap_or_default(),
     ))?;
     lazy.close().await.map_err(From::from)
 }
 
+/// A review entry of [`BulkReviewReq`].
+#[derive(Deserialize)]
+pub struct BulkReviewEntry {
+    /// Id of the post to review.
+    pub post: Id,
+    /// Review result status.
+    pub status: Status,
+    /// Review message.\
+    /// The field can be omitted.
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+/// Request body for bulk reviewing posts.
+///
+/// # Examples
+///
+/// ```json
+/// {
+///     "reviews": [
+///         {
+///             "post": 12,
+///             "status": "Approved",
+///         },
+///         {
+///             "post": 13,
+///             "status": "Rejected",
+///             "message": "Too many words.",
+///         },
+///     ],
+/// }
+/// ```
+#[derive(Deserialize)]
+pub struct BulkReviewReq {
+    /// Review entries, applied in order.
+    pub reviews: Vec<BulkReviewEntry>,
+}
+
+/// Result of a single review entry.
+#[derive(Serialize)]
+pub struct BulkReviewResult {
+    /// Id of the reviewed post.
+    pub post: Id,
+    /// Error message of this entry, if failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Response body for bulk reviewing posts.
+///
+/// # Examples
+///
+/// ```json
+/// {
+///     "results": [
+///         {
+///             "post": "12",
+///         },
+///         {
+///             "post": "13",
+///             "error": "invalid review result status",
+///         },
+///     ],
+/// }
+/// ```
+#[derive(Serialize)]
+pub struct BulkReviewRes {
+    /// Results of the review entries, in the
+    /// same order as the request.
+    pub results: Vec<BulkReviewResult>,
+}
+
+/// Reviews posts in bulk.
+///
+/// # Request
+///
+/// The request body is declared as [`BulkReviewReq`].
+///
+/// # Authorization
+///
+/// The request must be authorized with [`Permission::ReviewPost`].
+///
+/// # Response
+///
+/// The response body is declared as [`BulkReviewRes`].
+/// Each entry is validated and applied separately like [`review`],
+/// so failure of an entry won't affect others.
+pub async fn bulk_review<Io: IoHandle>(
+    auth: Auth,
+    State(Global { worlds, .. }): State<Global<Io>>,
+    Json(BulkReviewReq { reviews }): Json<BulkReviewReq>,
+) -> Result<Json<BulkReviewRes>, Error> {
+    let select = sd!(worlds.account, auth.account);
+    va!(auth, select => ReviewPost);
+
+    let mut results = Vec::with_capacity(reviews.len());
+    for BulkReviewEntry {
+        post,
+        status,
+        message,
+    } in reviews
+    {
+        results.push(BulkReviewResult {
+            post,
+            error: review_post(&worlds, post, status, message, auth.account)
+                .await
+                .err()
+                .map(|err| err.to_string()),
+        });
+    }
+    Ok(Json(BulkReviewRes { results }))
+}
+
 pub async fn remove<Io: IoHandle>(
     Path(id): Path<Id>,
     auth: Auth,
-    State(Global { worlds, .. }): State<Global<Io>>,
+    State(Global {
+        worlds,
+        resource_store,
+        resource_locks,
+        ..
+    }): State<Global<Io>>,
 ) -> Result<(), Error> {
     let select = sd!(worlds.account, auth.account);
     let this_lazy = va!(auth, select => Post);
@@ -612,24 +982,61 @@ pub async fn remove<Io: IoHandle>(
         return Err(Error::PostNotFound(id.0));
     }
 
-    if let Some(first) = post.resources().first().copied() {
-        let resources = post.resources();
-        let mut select = worlds
-            .resource
-            .select(0, first.0)
-            .and(1, 1)
-            .hints(resources.iter().copied().map(From::from));
-        for id in resources.iter().copied() {
-            select = select.plus(0, id.0)
-        }
-        let mut iter = select.iter();
-        while let Some(Ok(lazy)) = iter.next().await {
-            if resources.contains(&Id(lazy.id())) {
+    let links = post
+        .resources()
+        .iter()
+        .map(|res| (*res, vec![id.0]))
+        .collect();
+    lazy.destroy().await?;
+    unlink_resources(&worlds, &*resource_store, &resource_locks, links).await
+}
+
+/// Removes the given posts from the resources they use, as
+/// resource id => post ids.
+///
+/// Resources no longer used by any post are destroyed,
+/// and their files and storage usages are released.
+async fn unlink_resources<Io: IoHandle>(
+    worlds: &Worlds<Io>,
+    store: &dyn ResourceStore,
+    locks: &super::resource::ResourceLocks,
+    links: HashMap<Id, Vec<u64>>,
+) -> Result<(), Error> {
+    let Some(first) = links.keys().next().copied() else {
+        return Ok(());
+    };
+    let mut select = worlds
+        .resource
+        .select(0, first.0)
+        .and(1, 1..)
+        .hints(links.keys().copied().map(From::from));
+    for id in links.keys().copied() {
+        select = select.plus(0, id.0)
+    }
+    let mut iter = select.iter();
+    let mut released = vec![];
+    let unlinked = async {
+        while let Some(Ok(mut lazy)) = iter.next().await {
+            let Some(posts) = links.get(&Id(lazy.id())) else {
+                continue;
+            };
+            let resource = lazy.get_mut().await?;
+            for post in posts {
+                resource.unblock(*post);
+            }
+            if resource.is_blocked() {
+                lazy.close().await?;
+            } else {
+                released.push(super::resource::Released::from(&*resource));
                 lazy.destroy().await?;
             }
         }
+        Ok::<_, Error>(())
     }
-    lazy.destroy().await.map_err(From::from)
+    .await;
+    // Destroyed resources are released even if others failed.
+    super::resource::release(worlds, store, locks, released).await;
+    unlinked
 }
 
 #[derive(Deserialize)]
@@ -641,7 +1048,12 @@ pub enum BulkRemoveReq {
 
 pub async fn bulk_remove<Io: IoHandle>(
     auth: Auth,
-    State(Global { worlds, .. }): State<Global<Io>>,
+    State(Global {
+        worlds,
+        resource_store,
+        resource_locks,
+        ..
+    }): State<Global<Io>>,
     Json(req): Json<BulkRemoveReq>,
 ) -> Result<(), Error> {
     let select = sd!(worlds.account, auth.account);
@@ -651,7 +1063,7 @@ pub async fn bulk_remove<Io: IoHandle>(
         .await?
         .tags()
         .contains_permission(&Tag::Permission(Permission::RemovePost));
-    let mut resources_rm = vec![];
+    let mut links: HashMap<Id, Vec<u64>> = HashMap::new();
 
     match req {
         BulkRemoveReq::Posts { posts } => {
@@ -673,7 +1085,9 @@ pub async fn bulk_remove<Io: IoHandle>(
                     if post.creator() != Id(auth.account) && !permitted_rm {
                         continue;
                     }
-                    resources_rm.extend_from_slice(post.resources());
+                    for res in post.resources() {
+                        links.entry(*res).or_default().push(lazy.id());
+                    }
                     lazy.destroy().await?;
                 }
             }
@@ -694,7 +1108,9 @@ pub async fn bulk_remove<Io: IoHandle>(
             while let Some(Ok(lazy)) = iter.next().await {
                 if let Ok(post) = lazy.get().await {
                     if post.time().end() < &now.date() {
-                        resources_rm.extend_from_slice(post.resources());
+                        for res in post.resources() {
+                            links.entry(*res).or_default().push(lazy.id());
+                        }
                         lazy.destroy().await?;
                     }
                 }
@@ -702,22 +1118,5 @@ pub async fn bulk_remove<Io: IoHandle>(
         }
     }
 
-    if let Some(first) = resources_rm.first().copied() {
-        let mut select = worlds
-            .resource
-            .select(0, first.0)
-            .and(1, 1)
-            .hints(resources_rm.iter().copied().map(From::from));
-        for id in resources_rm.iter().copied() {
-            select = select.plus(0, id.0)
-        }
-        let mut iter = select.iter();
-        while let Some(Ok(lazy)) = iter.next().await {
-            if resources_rm.contains(&Id(lazy.id())) {
-                lazy.destroy().await?;
-            }
-        }
-    }
-
-    Ok(())
+    unlink_resources(&worlds, &*resource_store, &resource_locks, links).await
 }
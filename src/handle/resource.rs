@@ -1,23 +1,44 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, io::SeekFrom, ops::RangeInclusive, sync::Arc};
 
 use axum::{
     body::Body,
-    extract::{Path, State},
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
     Json,
 };
 use dmds::{IoHandle, StreamExt};
 use serde::{Deserialize, Serialize};
 
-#[allow(unused_imports)]
-use sms4_backend::account::Permission;
-
 use sms4_backend::{
-    resource::{Resource, Variant},
+    account::{Permission, Tag},
+    config::Config,
+    http_date,
+    post::Status,
+    resource::{
+        bundle,
+        meta::{ImageMeta, Metadata},
+        pdf,
+        sniff::Format,
+        store::{self, ResourceStore},
+        thumbnail,
+        usage::{Usage, UsageOwner},
+        video::VideoMeta,
+        Resource, TextStyle, UploadSessions, Variant,
+    },
     Id,
 };
-use tokio::{fs::File, io::BufReader};
+use time::OffsetDateTime;
+use tokio::{
+    fs::{File, OpenOptions},
+    io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt},
+    sync::Mutex,
+};
 
-use crate::{Auth, Error, Global};
+use crate::{
+    job::{GarbageReport, ScrubReport},
+    Auth, Error, Global, Worlds,
+};
 
 /// Request body for [`new_session`].
 ///
@@ -66,17 +87,34 @@ pub struct NewSessionRes {
 /// # Response
 ///
 /// The response body is declared as [`NewSessionRes`].
+///
+/// # Errors
+///
+/// - [`Error::QuotaExceeded`] if there is no storage quota left.
+/// - [`Error::InvalidResourceVariant`] if the variant has no payload file,
+/// like [`Variant::Text`].
 pub async fn new_session<Io: IoHandle>(
     auth: Auth,
     State(Global {
         worlds,
         resource_sessions,
+        config,
         ..
     }): State<Global<Io>>,
     Json(NewSessionReq { variant }): Json<NewSessionReq>,
 ) -> Result<Json<NewSessionRes>, Error> {
     let select = sd!(worlds.account, auth.account);
-    va!(auth, select => UploadResource);
+    let lazy = va!(auth, select => UploadResource);
+    variant.validate()?;
+    if !variant.has_payload() {
+        return Err(Error::InvalidResourceVariant(
+            "resources of this variant are not uploaded",
+        ));
+    }
+    let owners = UsageOwner::of(lazy.get().await?);
+    if quota_left(&worlds, &config, &owners).await? == Some(0) {
+        return Err(Error::QuotaExceeded { left: 0 });
+    }
 
     let resource = Resource::new(variant, Id(auth.account));
     let id = resource.id();
@@ -84,7 +122,100 @@ pub async fn new_session<Io: IoHandle>(
     Ok(Json(NewSessionRes { id: Id(id) }))
 }
 
-/// Response body for [`upload`].
+/// Request body for [`new_text`].
+///
+/// # Examples
+///
+/// ```json
+/// {
+///     "title": "Club Fair",
+///     "body": "Join us at the **playground** this Friday!",
+///     "style": {
+///         "align": "Center",
+///     },
+///     "duration": 15,
+/// }
+/// ```
+#[derive(Deserialize)]
+pub struct NewTextReq {
+    /// Title of the slide.
+    pub title: String,
+    /// Body of the slide, in Markdown.
+    pub body: String,
+    /// Display styling hints.\
+    /// The field can be omitted.
+    #[serde(default)]
+    pub style: TextStyle,
+    /// Duration the slide is displayed, as seconds.
+    pub duration: u32,
+}
+
+/// Creates a text slide resource.
+///
+/// Text slides are stored inline without payload files,
+/// so they don't need to be uploaded. Their titles and bodies
+/// are charged to the storage quotas.
+///
+/// # Request
+///
+/// The request body is declared as [`NewTextReq`].
+///
+/// # Authorization
+///
+/// The request must be authorized with [`Permission::UploadResource`].
+///
+/// # Response
+///
+/// The response body is declared as [`UploadRes`].
+///
+/// # Errors
+///
+/// - [`Error::InvalidResourceVariant`] if the title or body is too long,
+/// both of them are empty, or the style is invalid.
+/// - [`Error::QuotaExceeded`] if there is not enough storage quota left.
+pub async fn new_text<Io: IoHandle>(
+    auth: Auth,
+    State(Global {
+        worlds,
+        resource_locks,
+        config,
+        ..
+    }): State<Global<Io>>,
+    Json(NewTextReq {
+        title,
+        body,
+        style,
+        duration,
+    }): Json<NewTextReq>,
+) -> Result<Json<UploadRes>, Error> {
+    let select = sd!(worlds.account, auth.account);
+    let lazy = va!(auth, select => UploadResource);
+    let owners = UsageOwner::of(lazy.get().await?);
+    let size = (title.len() + body.len()) as u64;
+    let variant = Variant::Text {
+        title,
+        body,
+        style,
+        duration,
+    };
+    variant.validate()?;
+
+    let mut resource = Resource::new(variant, Id(auth.account));
+    let id = resource.id();
+    charge(&worlds, &config, &resource_locks, &owners, size).await?;
+    resource.set_metadata(Metadata {
+        size,
+        ..Default::default()
+    });
+    resource.set_charged(owners.clone());
+    if worlds.resource.try_insert(resource).await.is_err() {
+        refund(&worlds, &resource_locks, &owners, size).await;
+        return Err(Error::PermissionDenied);
+    }
+    Ok(Json(UploadRes { id: Id(id) }))
+}
+
+/// Response body for [`upload`] and [`finish_upload`].
 #[derive(Serialize)]
 pub struct UploadRes {
     /// Id of the resource.
@@ -93,6 +224,8 @@ pub struct UploadRes {
 
 /// Uploads a resource within the given session.
 ///
+/// For large files, use [`upload_chunk`] and [`finish_upload`] instead.
+///
 /// # Request
 ///
 /// The request body is the raw bytes of the resource.
@@ -104,160 +237,1305 @@ pub struct UploadRes {
 /// # Response
 ///
 /// The response body is declared as [`UploadRes`].
+///
+/// # Errors
+///
+/// - [`Error::ResourceContentMismatch`] if the format of the payload,
+/// detected from its magic bytes, doesn't match the declared variant.
+/// - [`Error::PermissionDenied`] if the session is not owned
+/// by the authorized account.
+/// - [`Error::PayloadTooLarge`] if the payload exceeds the size limit
+/// of the declared variant.
+/// - [`Error::QuotaExceeded`] if the payload exceeds the storage quota left.
 pub async fn upload<Io: IoHandle>(
+    Path(Id(id)): Path<Id>,
+    auth: Auth,
+    State(global): State<Global<Io>>,
+    headers: HeaderMap,
+    payload: Body,
+) -> Result<Json<UploadRes>, Error> {
+    let select = sd!(global.worlds.account, auth.account);
+    let lazy = va!(auth, select => UploadResource);
+    let owners = UsageOwner::of(lazy.get().await?);
+
+    write_session(
+        &global,
+        id,
+        Id(auth.account),
+        &owners,
+        0,
+        content_length(&headers),
+        payload,
+    )
+    .await?;
+    finalize(&global, id, Id(auth.account), &owners)
+        .await
+        .map(|id| Json(UploadRes { id }))
+}
+
+/// Request URL query parameters for [`upload_chunk`].
+///
+/// # Examples
+///
+/// ```json
+/// {
+///     "offset": 1048576,
+/// }
+/// ```
+#[derive(Deserialize)]
+pub struct UploadChunkParams {
+    /// Offset of the chunk in the payload, as bytes.\
+    /// This should not be greater than the number of bytes
+    /// received by the session.
+    pub offset: u64,
+}
+
+/// Response body for [`upload_chunk`] and [`upload_status`].
+///
+/// # Examples
+///
+/// ```json
+/// {
+///     "received": 2097152,
+/// }
+/// ```
+#[derive(Serialize)]
+pub struct UploadStatusRes {
+    /// Number of bytes received by the session.
+    pub received: u64,
+}
+
+/// Uploads a chunk of a resource within the given session.
+///
+/// Data after the offset received before will be discarded,
+/// so an interrupted upload could be resumed from the number
+/// of bytes received, which could be queried by [`upload_status`].
+///
+/// # Request
+///
+/// The request **query parameters** is declared as [`UploadChunkParams`],
+/// and the request body is the raw bytes of the chunk.
+///
+/// # Authorization
+///
+/// The request must be authorized with [`Permission::UploadResource`].
+///
+/// # Response
+///
+/// The response body is declared as [`UploadStatusRes`].
+///
+/// # Errors
+///
+/// - [`Error::ResourceUploadOffsetMismatch`] if the offset is greater than
+/// the number of bytes received.
+/// - [`Error::ResourceUploadBusy`] if another chunk of the session
+/// is being written.
+/// - [`Error::PermissionDenied`] if the session is not owned
+/// by the authorized account.
+/// - [`Error::PayloadTooLarge`] if the payload exceeds the size limit
+/// of the declared variant.
+/// - [`Error::QuotaExceeded`] if the payload exceeds the storage quota left.
+pub async fn upload_chunk<Io: IoHandle>(
+    Path(Id(id)): Path<Id>,
+    Query(UploadChunkParams { offset }): Query<UploadChunkParams>,
+    auth: Auth,
+    State(global): State<Global<Io>>,
+    headers: HeaderMap,
+    payload: Body,
+) -> Result<Json<UploadStatusRes>, Error> {
+    let select = sd!(global.worlds.account, auth.account);
+    let lazy = va!(auth, select => UploadResource);
+    let owners = UsageOwner::of(lazy.get().await?);
+
+    write_session(
+        &global,
+        id,
+        Id(auth.account),
+        &owners,
+        offset,
+        content_length(&headers),
+        payload,
+    )
+    .await
+    .map(|received| Json(UploadStatusRes { received }))
+}
+
+/// Gets the length of a request body from its `Content-Length` header.
+#[inline]
+fn content_length(headers: &HeaderMap) -> Option<u64> {
+    headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok())
+}
+
+/// Gets number of bytes received by the given session.
+///
+/// # Authorization
+///
+/// The request must be authorized with [`Permission::UploadResource`].
+///
+/// # Response
+///
+/// The response body is declared as [`UploadStatusRes`].
+pub async fn upload_status<Io: IoHandle>(
     Path(Id(id)): Path<Id>,
     auth: Auth,
     State(Global {
         worlds,
         resource_sessions,
-        config,
         ..
     }): State<Global<Io>>,
-    payload: axum::body::Body,
-) -> Result<Json<UploadRes>, Error> {
+) -> Result<Json<UploadStatusRes>, Error> {
     let select = sd!(worlds.account, auth.account);
     va!(auth, select => UploadResource);
 
-    let mut hasher = highway::PortableHash::default();
-    let buf_path = config.resource_path.join(
-        resource_sessions
-            .lock()
-            .await
-            .buf_name(id)
-            .ok_or(Error::ResourceUploadSessionNotFound(id))?,
-    );
-    let mut file = tokio::fs::File::create(&buf_path)
+    resource_sessions
+        .lock()
         .await
-        .map_err(|_| Error::ResourceSaveFailed)?;
-    let mut stream = http_body_util::BodyStream::new(payload);
+        .received(id, Id(auth.account))
+        .map(|received| Json(UploadStatusRes { received }))
+}
+
+/// Finishes a chunked upload within the given session.
+///
+/// # Authorization
+///
+/// The request must be authorized with [`Permission::UploadResource`].
+///
+/// # Response
+///
+/// The response body is declared as [`UploadRes`].
+///
+/// # Errors
+///
+/// - [`Error::ResourceContentMismatch`] if the format of the payload,
+/// detected from its magic bytes, doesn't match the declared variant.
+/// - [`Error::QuotaExceeded`] if the payload exceeds the storage quota left.
+///
+/// The received data will be discarded if the payload is rejected,
+/// so it should be uploaded again.
+pub async fn finish_upload<Io: IoHandle>(
+    Path(Id(id)): Path<Id>,
+    auth: Auth,
+    State(global): State<Global<Io>>,
+) -> Result<Json<UploadRes>, Error> {
+    let select = sd!(global.worlds.account, auth.account);
+    let lazy = va!(auth, select => UploadResource);
+    let owners = UsageOwner::of(lazy.get().await?);
+
+    finalize(&global, id, Id(auth.account), &owners)
+        .await
+        .map(|id| Json(UploadRes { id }))
+}
+
+/// Ends writing of an upload session as failed when dropped,
+/// in case the request is cancelled while writing.
+struct WriteGuard {
+    sessions: Option<Arc<Mutex<UploadSessions>>>,
+    id: u64,
+}
+
+impl Drop for WriteGuard {
+    fn drop(&mut self) {
+        if let Some(sessions) = self.sessions.take() {
+            let id = self.id;
+            tokio::spawn(async move { sessions.lock().await.end_write(id, None) });
+        }
+    }
+}
+
+/// Writes a request body into the buffer of a session from given offset,
+/// and returns the number of bytes received by the session.
+///
+/// The payload is limited by the size limit of the declared variant,
+/// and the storage quota left of given owners. Bodies declaring
+/// a larger length are rejected before being read.
+async fn write_session<Io: IoHandle>(
+    Global {
+        worlds,
+        resource_sessions,
+        config,
+        ..
+    }: &Global<Io>,
+    id: u64,
+    user: Id,
+    owners: &[UsageOwner],
+    offset: u64,
+    content_length: Option<u64>,
+    payload: Body,
+) -> Result<u64, Error> {
+    let limit = config
+        .payload_limit
+        .of(resource_sessions.lock().await.variant(id, user)?);
+    let left = quota_left(worlds, config, owners).await?;
+    let max = left.map_or(limit, |left| left.min(limit));
+    if content_length.is_some_and(|len| offset.saturating_add(len) > max) {
+        return Err(too_large(limit, left));
+    }
+
+    let buf_name = resource_sessions
+        .lock()
+        .await
+        .begin_write(id, user, offset)?;
+    let mut guard = WriteGuard {
+        sessions: Some(resource_sessions.clone()),
+        id,
+    };
+    let result = write_buf(&config.resource_path.join(buf_name), offset, payload, max).await;
+    guard.sessions = None;
+    resource_sessions
+        .lock()
+        .await
+        .end_write(id, result.as_ref().ok().copied());
+    result.map(|len| offset + len).map_err(|err| match err {
+        Error::PayloadTooLarge { .. } => too_large(limit, left),
+        err => err,
+    })
+}
+
+/// Gets the error of a payload exceeding the lesser one
+/// of the size limit and the storage quota left.
+#[inline]
+fn too_large(limit: u64, left: Option<u64>) -> Error {
+    match left {
+        Some(left) if left < limit => Error::QuotaExceeded { left },
+        _ => Error::PayloadTooLarge { max: limit },
+    }
+}
 
-    const MAX_PAYLOAD_LEN: usize = 50 * 1024 * 1024;
+/// Writes a request body into a buffer file from given offset,
+/// and returns the number of bytes written.
+///
+/// Data after the offset will be discarded, and the total length
+/// is limited by `max`.
+async fn write_buf(
+    path: &std::path::Path,
+    offset: u64,
+    payload: Body,
+    max: u64,
+) -> Result<u64, Error> {
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+        .await
+        .map_err(|_| Error::ResourceSaveFailed)?;
+    file.set_len(offset)
+        .await
+        .map_err(|_| Error::ResourceSaveFailed)?;
+    file.seek(SeekFrom::Start(offset))
+        .await
+        .map_err(|_| Error::ResourceSaveFailed)?;
 
-    let mut len = 0_usize;
+    let mut stream = http_body_util::BodyStream::new(payload);
+    let mut len = 0_u64;
     while let Some(chunk) = stream
         .try_next()
         .await
         .map_err(|_| Error::ResourceSaveFailed)?
     {
         let chunk = chunk.into_data().map_err(|_| Error::ResourceSaveFailed)?;
-        len += chunk.len();
-        highway::HighwayHash::append(&mut hasher, &chunk);
-        tokio::io::AsyncWriteExt::write_all(&mut file, &chunk)
+        len += chunk.len() as u64;
+        if offset + len > max {
+            return Err(Error::PayloadTooLarge { max });
+        }
+        file.write_all(&chunk)
             .await
             .map_err(|_| Error::ResourceSaveFailed)?;
     }
-    if len > MAX_PAYLOAD_LEN {
-        drop(file);
-        let _ = tokio::fs::remove_file(buf_path).await;
-        return Err(Error::PayloadTooLarge {
-            max: MAX_PAYLOAD_LEN,
-        });
-    }
 
-    tokio::io::AsyncWriteExt::flush(&mut file)
-        .await
-        .map_err(|_| Error::ResourceSaveFailed)?;
+    file.flush().await.map_err(|_| Error::ResourceSaveFailed)?;
     file.sync_data()
         .await
         .map_err(|_| Error::ResourceSaveFailed)?;
+    Ok(len)
+}
 
-    let resource = resource_sessions
-        .lock()
-        .await
-        .accept(Id(id), hasher, Id(auth.account))?;
-    let id = Id(resource.id());
-    let path = config.resource_path.join(resource.file_name());
-    tokio::fs::rename(buf_path, path)
+/// Hashes a file, and returns the hasher, leading bytes
+/// for detecting its format, and its length.
+async fn digest(path: &std::path::Path) -> Result<(highway::PortableHash, Vec<u8>, u64), Error> {
+    let mut file = File::open(path)
         .await
         .map_err(|_| Error::ResourceSaveFailed)?;
-
-    worlds
-        .resource
-        .try_insert(resource)
-        .await
-        .map_err(|_| Error::PermissionDenied)?;
-    Ok(Json(UploadRes { id }))
+    let mut hasher = highway::PortableHash::default();
+    let mut header = Vec::with_capacity(Format::HEADER_LEN);
+    let mut len = 0_u64;
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let read = file
+            .read(&mut buf)
+            .await
+            .map_err(|_| Error::ResourceSaveFailed)?;
+        if read == 0 {
+            break;
+        }
+        let chunk = &buf[..read];
+        len += read as u64;
+        if header.len() < Format::HEADER_LEN {
+            let rest = (Format::HEADER_LEN - header.len()).min(read);
+            header.extend_from_slice(&chunk[..rest]);
+        }
+        highway::HighwayHash::append(&mut hasher, chunk);
+    }
+    Ok((hasher, header, len))
 }
 
-/// Gets payload of a resource.
-///
-/// # Authorization
-///
-/// The request must be authorized with [`Permission::GetPubPost`].
-///
-/// # Response
-///
-/// The response body is the raw bytes of the resource.
-///
-/// # Errors
+/// Finalizes the upload of a session, and returns id of the resource.
 ///
-/// - [`Error::ResourceNotFound`] if the resource with the given id does not exist.
-/// - [`Error::PermissionDenied`] if the resource is not blocked **and** is not owned by the authorized account.
-pub async fn get_payload<Io: IoHandle>(
-    Path(Id(id)): Path<Id>,
-    auth: Auth,
-    State(Global { worlds, config, .. }): State<Global<Io>>,
-) -> Result<Body, Error> {
-    let select = sd!(worlds.account, auth.account);
-    va!(auth, select => GetPubPost);
-    let select = sd!(worlds.resource, id);
-    let lazy = gd!(select, id).ok_or(Error::ResourceNotFound(id))?;
-    let resource = lazy.get().await?;
-    if resource.owner() != Id(auth.account) && !resource.is_blocked() {
-        return Err(Error::PermissionDenied);
+/// The payload is inspected, moved into the resource store and
+/// recorded into the database, and charged to given owners.
+async fn finalize<Io: IoHandle>(
+    Global {
+        worlds,
+        resource_sessions,
+        resource_store,
+        resource_locks,
+        config,
+        ..
+    }: &Global<Io>,
+    id: u64,
+    user: Id,
+    owners: &[UsageOwner],
+) -> Result<Id, Error> {
+    // Take the session out, so it could not be written
+    // or finalized by others in the meantime.
+    let mut resource = resource_sessions.lock().await.take(id, user)?;
+    let buf_path = config.resource_path.join(resource.buf_name());
+    let inspected = async {
+        let (hasher, header, len) = digest(&buf_path).await?;
+        // Charge before inspecting, so other sessions accepted
+        // in the meantime could not exceed the quota.
+        charge(worlds, config, resource_locks, owners, len).await?;
+        let metadata = match inspect(resource.variant_mut(), &buf_path, &header, len).await {
+            Ok(metadata) => metadata,
+            Err(err) => {
+                refund(worlds, resource_locks, owners, len).await;
+                return Err(err);
+            }
+        };
+        // Unpacked files of HTML bundles are charged once known.
+        let unpacked = metadata.charged_size() - len;
+        if unpacked > 0 {
+            if let Err(err) = charge(worlds, config, resource_locks, owners, unpacked).await {
+                refund(worlds, resource_locks, owners, len).await;
+                return Err(err);
+            }
+        }
+        Ok((hasher, metadata))
     }
+    .await;
+    let (hasher, metadata) = match inspected {
+        Ok(inspected) => inspected,
+        Err(err) => {
+            let _ = tokio::fs::remove_file(&buf_path).await;
+            resource_sessions.lock().await.insert(resource);
+            return Err(err);
+        }
+    };
 
-    // Read the file and response it asynchronously.
-    let path = config.resource_path.join(resource.file_name());
-    Ok(Body::from_stream(tokio_util::io::ReaderStream::new(
-        BufReader::new(File::open(path).await.map_err(|_| Error::Unknown)?),
-    )))
-}
+    resource.accept(hasher, user);
+    let charged = metadata.charged_size();
+    let size = metadata.size;
+    let format = metadata.format;
+    let orientation = metadata.image.map_or(1, |image| image.orientation);
+    resource.set_metadata(metadata);
+    resource.set_charged(owners.to_vec());
+    let id = Id(resource.id());
+    let hash = resource.hash();
+    let file_name = resource.file_name();
+    let thumbnails = thumbnail::SIZES.map(|size| resource.thumbnail_name(size));
 
-/// Information of a resource.
-#[derive(Serialize)]
-pub struct Info {
-    /// The resource variant.
-    pub variant: Variant,
-}
+    // Files are checked and recorded exclusively,
+    // so a shared file won't be released in the meantime.
+    let files_lock = resource_locks.files.lock().await;
+    let stored = if is_file_referred(worlds, hash).await {
+        match same_content(&**resource_store, &file_name, &buf_path).await {
+            Ok(true) => true,
+            Ok(false) => {
+                tracing::error!("content hash collision of {file_name}");
+                let _ = tokio::fs::remove_file(&buf_path).await;
+                refund(worlds, resource_locks, owners, charged).await;
+                return Err(Error::ResourceSaveFailed);
+            }
+            // The shared file is missing, so it's stored again.
+            Err(_) => false,
+        }
+    } else {
+        false
+    };
+    // Thumbnails of a shared file are already generated.
+    let thumbnail_format = format.filter(|format| {
+        !stored
+            && size <= thumbnail::MAX_SOURCE_LEN
+            && matches!(
+                format,
+                Format::Png | Format::Jpeg | Format::WebP | Format::Gif | Format::Pdf
+            )
+    });
+    let unpack = matches!(resource.variant(), Variant::Html { .. }) && !stored;
+    let released = Released::from(&resource);
 
-pub async fn get_info<Io: IoHandle>(
-    Path(Id(id)): Path<Id>,
-    auth: Auth,
-    State(Global { worlds, .. }): State<Global<Io>>,
-) -> Result<Json<Info>, Error> {
-    let select = sd!(worlds.account, auth.account);
-    va!(auth, select => GetPubPost);
-    let select = sd!(worlds.resource, id).and(1, 1);
-    let lazy = gd!(select, id).ok_or(Error::ResourceNotFound(id))?;
-    let resource = lazy.get().await?;
-    if resource.owner() != Id(auth.account) && !resource.is_blocked() {
+    // Insert the record before moving the file, so the file
+    // won't be released by others in the meantime.
+    if worlds.resource.try_insert(resource).await.is_err() {
+        refund(worlds, resource_locks, owners, charged).await;
         return Err(Error::PermissionDenied);
     }
-    Ok(Json(Info {
-        variant: resource.variant().clone(),
-    }))
+    drop(files_lock);
+    let saved = async {
+        if unpack {
+            unpack_bundle(&**resource_store, hash, &buf_path).await?;
+        }
+        if stored {
+            let _ = tokio::fs::remove_file(&buf_path).await;
+        } else if let Err(err) = resource_store.put(&file_name, &buf_path).await {
+            tracing::error!("failed to store {file_name}: {err}");
+            return Err(Error::ResourceSaveFailed);
+        }
+        Ok(())
+    }
+    .await;
+    if let Err(err) = saved {
+        // Destroy the record, so its usage and stored files are released.
+        let _ = tokio::fs::remove_file(&buf_path).await;
+        let select = sd!(worlds.resource, id.0);
+        if let Some(lazy) = gd!(select, id.0) {
+            if let Err(err) = lazy.destroy().await {
+                tracing::error!("failed to destroy resource {}: {err}", id.0);
+            }
+        }
+        release(worlds, &**resource_store, resource_locks, vec![released]).await;
+        return Err(err);
+    }
+    if let Some(format) = thumbnail_format {
+        tokio::spawn(save_thumbnails(
+            resource_store.clone(),
+            file_name,
+            format,
+            orientation,
+            thumbnails,
+        ));
+    }
+    Ok(id)
 }
 
-/// Request body for [`bulk_get_info`].
-#[derive(Deserialize)]
-pub struct BulkGetInfoReq {
-    /// Ids of the resources.
-    pub ids: Box<[Id]>,
+/// Whether a stored file has the same content as a local file.
+///
+/// Errors if the stored file could not be read.
+async fn same_content(
+    store: &dyn ResourceStore,
+    name: &str,
+    path: &std::path::Path,
+) -> std::io::Result<bool> {
+    let mut stream = store.get(name).await?;
+    let mut file = File::open(path).await?;
+    let mut buf = vec![];
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        buf.resize(chunk.len(), 0);
+        match file.read_exact(&mut buf).await {
+            Ok(_) if buf == *chunk => {}
+            Ok(_) => return Ok(false),
+            Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(false),
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(file.read(&mut [0u8]).await? == 0)
 }
 
-pub async fn bulk_get_info<Io: IoHandle>(
-    auth: Auth,
-    State(Global { worlds, .. }): State<Global<Io>>,
-    Json(BulkGetInfoReq { ids }): Json<BulkGetInfoReq>,
-) -> Result<Json<HashMap<u64, Info>>, Error> {
-    let select = sd!(worlds.account, auth.account);
-    va!(auth, select => GetPubPost);
+/// Unpacks files of an HTML bundle from a local file into the store.
+///
+/// Each file is streamed into a part file next to the bundle, which
+/// is then put into the store, so files are never held in memory.
+async fn unpack_bundle(
+    store: &dyn ResourceStore,
+    hash: u64,
+    path: &std::path::Path,
+) -> Result<(), Error> {
+    let (tx, mut rx) = tokio::sync::mpsc::channel::<String>(1);
+    let (stored_tx, stored_rx) = std::sync::mpsc::channel::<()>();
+    let part = path.with_extension("part");
+    let unpacking = {
+        let path = path.to_owned();
+        let part = part.clone();
+        tokio::task::spawn_blocking(move || {
+            let file = std::fs::File::open(path).map_err(|_| Error::ResourceSaveFailed)?;
+            bundle::unpack(std::io::BufReader::new(file), |path, content| {
+                let mut dst =
+                    std::fs::File::create(&part).map_err(|_| Error::ResourceSaveFailed)?;
+                std::io::copy(content, &mut dst).map_err(|err| {
+                    if err.kind() == std::io::ErrorKind::InvalidData {
+                        Error::InvalidHtmlBundle("corrupted zip archive")
+                    } else {
+                        Error::ResourceSaveFailed
+                    }
+                })?;
+                tx.blocking_send(path)
+                    .map_err(|_| Error::ResourceSaveFailed)?;
+                // The part file is reused by the next file.
+                stored_rx.recv().map_err(|_| Error::ResourceSaveFailed)
+            })
+        })
+    };
+    let mut result = Ok(());
+    while let Some(path) = rx.recv().await {
+        let name = Resource::bundle_file_name_of(hash, &path);
+        if let Err(err) = store.put(&name, &part).await {
+            tracing::error!("failed to store {name}: {err}");
+            result = Err(Error::ResourceSaveFailed);
+            break;
+        }
+        let _ = stored_tx.send(());
+    }
+    // Unpacking stops as the channels are closed.
+    drop(rx);
+    drop(stored_tx);
+    let unpacked = unpacking.await.map_err(|_| Error::Unknown)?;
+    let _ = tokio::fs::remove_file(&part).await;
+    result.and(unpacked)
+}
+
+/// Whether there is a resource referring to the file
+/// with given content hash.
+async fn is_file_referred<Io: IoHandle>(worlds: &Worlds<Io>, hash: u64) -> bool {
+    let select = worlds.resource.select(2, hash);
+    let mut iter = select.iter();
+    while let Some(Ok(lazy)) = iter.next().await {
+        if lazy
+            .get()
+            .await
+            .is_ok_and(|resource| resource.hash() == hash && resource.variant().has_payload())
+        {
+            return true;
+        }
+    }
+    false
+}
+
+/// A destroyed resource, whose file and storage usage should be released.
+#[derive(Debug, Clone)]
+pub struct Released {
+    /// Content hash of the resource, or `None`
+    /// if the resource has no payload file.
+    pub hash: Option<u64>,
+    /// Paths of files in the unpacked HTML bundle of the resource.
+    pub bundle: Box<[String]>,
+    /// Usage owners charged for the payload.
+    pub charged: Vec<UsageOwner>,
+    /// Size of the payload, as bytes.
+    pub size: u64,
+}
+
+impl From<&Resource> for Released {
+    #[inline]
+    fn from(resource: &Resource) -> Self {
+        Self {
+            hash: Some(resource.hash()).filter(|_| resource.variant().has_payload()),
+            bundle: match resource.variant() {
+                Variant::Html { files, .. } => files.clone(),
+                _ => Box::new([]),
+            },
+            charged: resource.charged().to_vec(),
+            size: resource.metadata().charged_size(),
+        }
+    }
+}
+
+/// Locks of resource states shared by requests.
+#[derive(Debug, Default)]
+pub struct ResourceLocks {
+    /// Held while checking whether a resource file is referred,
+    /// until it's recorded or removed.
+    pub(crate) files: Mutex<()>,
+    /// Held while updating storage usages, so quotas
+    /// are checked and charged atomically.
+    pub(crate) usages: Mutex<()>,
+}
+
+/// Releases destroyed resources.
+///
+/// Their storage usages are released from the charged owners, and files
+/// no resource refers to anymore are removed, including their thumbnails
+/// and unpacked HTML bundles.
+///
+/// As the records are already destroyed, failures are logged
+/// instead of being returned.
+pub async fn release<Io: IoHandle>(
+    worlds: &Worlds<Io>,
+    store: &dyn ResourceStore,
+    locks: &ResourceLocks,
+    released: Vec<Released>,
+) {
+    let mut sizes: HashMap<UsageOwner, u64> = HashMap::new();
+    for r in &released {
+        for owner in &r.charged {
+            *sizes.entry(owner.clone()).or_default() += r.size;
+        }
+    }
+    {
+        let _usages_lock = locks.usages.lock().await;
+        for (owner, size) in sizes {
+            if let Err(err) = update_usages(worlds, std::slice::from_ref(&owner), |usage| {
+                usage.release(size)
+            })
+            .await
+            {
+                tracing::error!("failed to release usage of {owner:?}: {err}");
+            }
+        }
+    }
+
+    let _files_lock = locks.files.lock().await;
+    for (hash, files) in released
+        .into_iter()
+        .filter_map(|r| r.hash.map(|hash| (hash, r.bundle)))
+    {
+        if is_file_referred(worlds, hash).await {
+            continue;
+        }
+        for path in files.iter() {
+            let _ = store
+                .delete(&Resource::bundle_file_name_of(hash, path))
+                .await;
+        }
+        let name = Resource::file_name_of(hash);
+        if let Err(err) = store.delete(&name).await {
+            tracing::error!("failed to remove {name}: {err}");
+        }
+        for size in thumbnail::SIZES {
+            let _ = store.delete(&Resource::thumbnail_name_of(hash, size)).await;
+        }
+    }
+}
+
+/// Gets used bytes of given owners, in the same order.
+async fn usages<Io: IoHandle>(
+    worlds: &Worlds<Io>,
+    owners: &[UsageOwner],
+) -> Result<Vec<u64>, Error> {
+    let mut bytes = vec![0; owners.len()];
+    let Some(first) = owners.first() else {
+        return Ok(bytes);
+    };
+    let mut select = worlds.usage.select(0, first.key());
+    for owner in &owners[1..] {
+        select = select.plus(0, owner.key());
+    }
+    let mut iter = select.iter();
+    while let Some(Ok(lazy)) = iter.next().await {
+        let usage = lazy.get().await?;
+        if let Some(i) = owners.iter().position(|o| o == usage.owner()) {
+            bytes[i] += usage.bytes();
+        }
+    }
+    Ok(bytes)
+}
+
+/// Gets bytes left under the storage quotas of given owners.
+///
+/// Returns `None` if none of the owners is limited.
+async fn quota_left<Io: IoHandle>(
+    worlds: &Worlds<Io>,
+    config: &Config,
+    owners: &[UsageOwner],
+) -> Result<Option<u64>, Error> {
+    Ok(owners
+        .iter()
+        .zip(usages(worlds, owners).await?)
+        .filter_map(|(owner, used)| config.quota.of(owner).map(|q| q.saturating_sub(used)))
+        .min())
+}
+
+/// Charges given bytes to given owners if they are
+/// within the storage quotas.
+async fn charge<Io: IoHandle>(
+    worlds: &Worlds<Io>,
+    config: &Config,
+    locks: &ResourceLocks,
+    owners: &[UsageOwner],
+    size: u64,
+) -> Result<(), Error> {
+    let _usages_lock = locks.usages.lock().await;
+    if let Some(left) = quota_left(worlds, config, owners).await? {
+        if size > left {
+            return Err(Error::QuotaExceeded { left });
+        }
+    }
+    update_usages(worlds, owners, |usage| usage.charge(size)).await
+}
+
+/// Releases given bytes charged by [`charge`] from given owners.
+///
+/// Failures are logged instead of being returned.
+async fn refund<Io: IoHandle>(
+    worlds: &Worlds<Io>,
+    locks: &ResourceLocks,
+    owners: &[UsageOwner],
+    size: u64,
+) {
+    let _usages_lock = locks.usages.lock().await;
+    if let Err(err) = update_usages(worlds, owners, |usage| usage.release(size)).await {
+        tracing::error!("failed to refund usages of {owners:?}: {err}");
+    }
+}
+
+/// Updates usages of given owners, creating them if not exist.
+///
+/// [`ResourceLocks::usages`] should be held.
+async fn update_usages<Io: IoHandle>(
+    worlds: &Worlds<Io>,
+    owners: &[UsageOwner],
+    f: impl Fn(&mut Usage),
+) -> Result<(), Error> {
+    let mut found = vec![false; owners.len()];
+    let Some(first) = owners.first() else {
+        return Ok(());
+    };
+    let mut select = worlds.usage.select(0, first.key());
+    for owner in &owners[1..] {
+        select = select.plus(0, owner.key());
+    }
+    let mut iter = select.iter();
+    while let Some(Ok(mut lazy)) = iter.next().await {
+        let owner = lazy.get().await?.owner().clone();
+        let Some(i) = owners.iter().position(|o| *o == owner) else {
+            continue;
+        };
+        if !found[i] {
+            f(lazy.get_mut().await?);
+            lazy.close().await?;
+            found[i] = true;
+        }
+    }
+    for (owner, _) in owners.iter().zip(found).filter(|(_, found)| !found) {
+        let mut usage = Usage::new(owner.clone());
+        f(&mut usage);
+        worlds.usage.insert(usage).await?;
+    }
+    Ok(())
+}
+
+/// Inspects the uploaded payload of a session, validating it against
+/// the declared variant and extracting its metadata.
+///
+/// Pages of a PDF file, duration of a video and files of an
+/// HTML bundle are filled into the declared variant.
+async fn inspect(
+    variant: &mut Variant,
+    path: &std::path::Path,
+    header: &[u8],
+    len: u64,
+) -> Result<Metadata, Error> {
+    let format = Format::detect(header)
+        .filter(|f| f.matches(variant))
+        .ok_or(Error::ResourceContentMismatch)?;
+
+    let mut metadata = Metadata {
+        size: len,
+        format: Some(format),
+        image: None,
+        video: None,
+        unpacked: None,
+    };
+    match &*variant {
+        Variant::Pdf { .. } => {
+            if len > pdf::MAX_PARSED_LEN {
+                return Err(Error::PayloadTooLarge {
+                    max: pdf::MAX_PARSED_LEN,
+                });
+            }
+            let bytes = tokio::fs::read(path)
+                .await
+                .map_err(|_| Error::ResourceSaveFailed)?;
+            let pages = tokio::task::spawn_blocking(move || pdf::count_pages(&bytes))
+                .await
+                .map_err(|_| Error::Unknown)?
+                .ok_or(Error::ResourceContentMismatch)?;
+            variant.fit_pdf_pages(pages)?;
+        }
+        Variant::Image { .. } => {
+            let mut bytes = vec![];
+            File::open(path)
+                .await
+                .map_err(|_| Error::ResourceSaveFailed)?
+                .take(ImageMeta::MAX_HEADER_LEN)
+                .read_to_end(&mut bytes)
+                .await
+                .map_err(|_| Error::ResourceSaveFailed)?;
+            metadata.image = ImageMeta::extract(format, &bytes);
+        }
+        Variant::Html { entry, .. } => {
+            let entry = entry.clone();
+            let path = path.to_owned();
+            let (files, unpacked) = tokio::task::spawn_blocking(move || {
+                let file = std::fs::File::open(path).map_err(|_| Error::ResourceSaveFailed)?;
+                bundle::validate(std::io::BufReader::new(file), &entry)
+            })
+            .await
+            .map_err(|_| Error::Unknown)??;
+            variant.fill_html_files(files);
+            metadata.unpacked = Some(unpacked);
+        }
+        Variant::Video { duration } => {
+            let unknown_duration = *duration == 0;
+            let path = path.to_owned();
+            let video = tokio::task::spawn_blocking(move || {
+                let file = std::fs::File::open(path).ok()?;
+                VideoMeta::probe(format, std::io::BufReader::new(file))
+            })
+            .await
+            .map_err(|_| Error::Unknown)?
+            .ok_or(Error::ResourceContentMismatch)?;
+            match video.duration_secs() {
+                Some(actual) => variant.fit_video_duration(actual)?,
+                None if unknown_duration => {
+                    return Err(Error::InvalidResourceVariant(
+                        "video duration is neither declared nor found in the container",
+                    ))
+                }
+                None => {}
+            }
+            metadata.video = Some(video);
+        }
+        Variant::Text { .. } => {}
+    }
+    Ok(metadata)
+}
+
+/// Generates thumbnails of a stored resource file, and saves them
+/// into the store with given names in order of [`thumbnail::SIZES`].
+///
+/// The file is read into memory, so it should not be
+/// longer than [`thumbnail::MAX_SOURCE_LEN`].
+///
+/// Errors are logged, as a resource without thumbnails
+/// is still usable.
+async fn save_thumbnails(
+    store: Arc<dyn ResourceStore>,
+    name: String,
+    format: Format,
+    orientation: u8,
+    thumbnails: [String; thumbnail::SIZES.len()],
+) {
+    let bytes = match store::read(&*store, &name).await {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            tracing::error!("failed to read {name} for thumbnails: {err}");
+            return;
+        }
+    };
+    let Ok(Some(generated)) =
+        tokio::task::spawn_blocking(move || thumbnail::generate(format, &bytes, orientation)).await
+    else {
+        tracing::warn!("no thumbnail generated for {}", thumbnails[0]);
+        return;
+    };
+    for ((_, buf), thumbnail) in generated.into_iter().zip(thumbnails) {
+        if let Err(err) = store.put_bytes(&thumbnail, buf.into()).await {
+            tracing::error!("failed to save thumbnail {thumbnail}: {err}");
+        }
+    }
+}
+
+/// Gets payload of a resource.
+///
+/// # Request
+///
+/// The request could contain a single `Range` of bytes, and
+/// conditional headers `If-None-Match` and `If-Modified-Since`.
+///
+/// # Authorization
+///
+/// The request must be authorized with [`Permission::GetPubPost`].
+///
+/// # Response
+///
+/// The response body is the raw bytes of the resource,
+/// or the requested range of them with `206 Partial Content`.
+///
+/// The response contains an `ETag` derived from the content hash,
+/// so it could be cached by clients and revalidated with `304 Not Modified`.
+///
+/// # Errors
+///
+/// - [`Error::ResourceNotFound`] if the resource with the given id does not exist.
+/// - [`Error::PermissionDenied`] if the resource is not blocked **and** is not owned by the authorized account.
+/// - [`Error::ResourceNoPayload`] if the resource is stored inline, like [`Variant::Text`].
+/// - `416 Range Not Satisfiable` if the range is out of the payload.
+pub async fn get_payload<Io: IoHandle>(
+    Path(Id(id)): Path<Id>,
+    auth: Auth,
+    State(Global {
+        worlds,
+        resource_store,
+        ..
+    }): State<Global<Io>>,
+    headers: HeaderMap,
+) -> Result<Response, Error> {
+    let select = sd!(worlds.account, auth.account);
+    va!(auth, select => GetPubPost);
+    let select = sd!(worlds.resource, id);
+    let lazy = gd!(select, id).ok_or(Error::ResourceNotFound(id))?;
+    let resource = lazy.get().await?;
+    if resource.owner() != Id(auth.account) && !resource.is_blocked() {
+        return Err(Error::PermissionDenied);
+    }
+    if !resource.variant().has_payload() {
+        return Err(Error::ResourceNoPayload(id));
+    }
+
+    let name = resource.file_name();
+    let file_meta = resource_store
+        .head(&name)
+        .await
+        .map_err(|_| Error::Unknown)?;
+    let len = file_meta.len;
+    // HTTP dates are accurate to seconds.
+    let last_modified = file_meta
+        .modified
+        .and_then(|t| OffsetDateTime::from(t).replace_nanosecond(0).ok());
+    let etag = format!("\"{:x}\"", resource.hash());
+
+    let mut builder = Response::builder()
+        .header(header::ETAG, &etag)
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header(
+            header::CONTENT_TYPE,
+            resource
+                .metadata()
+                .format
+                .map_or_else(|| resource.variant().mime(), Format::mime),
+        );
+    if let Some(date) = last_modified.and_then(http_date::format) {
+        builder = builder.header(header::LAST_MODIFIED, date);
+    }
+
+    // `If-None-Match` takes precedence over `If-Modified-Since`.
+    let not_modified = if let Some(value) = headers.get(header::IF_NONE_MATCH) {
+        value.to_str().is_ok_and(|value| {
+            value
+                .split(',')
+                .map(str::trim)
+                .any(|tag| tag == "*" || tag.strip_prefix("W/").unwrap_or(tag) == etag)
+        })
+    } else {
+        headers
+            .get(header::IF_MODIFIED_SINCE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(http_date::parse)
+            .zip(last_modified)
+            .is_some_and(|(since, modified)| modified <= since)
+    };
+    if not_modified {
+        return builder
+            .status(StatusCode::NOT_MODIFIED)
+            .body(Body::empty())
+            .map_err(|_| Error::Unknown);
+    }
+
+    let range = headers
+        .get(header::RANGE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| parse_range(value, len));
+    let res = match range {
+        None => builder
+            .header(header::CONTENT_LENGTH, len)
+            .body(Body::from_stream(
+                resource_store
+                    .get(&name)
+                    .await
+                    .map_err(|_| Error::Unknown)?,
+            )),
+        Some(Ok(range)) => {
+            let range_len = range.end() - range.start() + 1;
+            let content_range = format!("bytes {}-{}/{len}", range.start(), range.end());
+            builder
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(header::CONTENT_RANGE, content_range)
+                .header(header::CONTENT_LENGTH, range_len)
+                .body(Body::from_stream(
+                    resource_store
+                        .get_range(&name, range)
+                        .await
+                        .map_err(|_| Error::Unknown)?,
+                ))
+        }
+        Some(Err(())) => builder
+            .status(StatusCode::RANGE_NOT_SATISFIABLE)
+            .header(header::CONTENT_RANGE, format!("bytes */{len}"))
+            .body(Body::empty()),
+    };
+    res.map_err(|_| Error::Unknown)
+}
+
+/// Parses a `Range` header value against a payload
+/// with given length.
+///
+/// Returns `None` if the header should be ignored, like
+/// multiple ranges or other units, and `Some(Err(()))`
+/// if the range is not satisfiable.
+pub(crate) fn parse_range(value: &str, len: u64) -> Option<Result<RangeInclusive<u64>, ()>> {
+    let spec = value.strip_prefix("bytes=")?.trim();
+    if spec.contains(',') {
+        return None;
+    }
+    let (start, end) = spec.split_once('-')?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", suffix) => {
+            let suffix: u64 = suffix.parse().ok()?;
+            if suffix == 0 {
+                return Some(Err(()));
+            }
+            (len.saturating_sub(suffix), u64::MAX)
+        }
+        (start, "") => (start.parse().ok()?, u64::MAX),
+        (start, end) => {
+            let (start, end): (u64, u64) = (start.parse().ok()?, end.parse().ok()?);
+            if end < start {
+                return None;
+            }
+            (start, end)
+        }
+    };
+    if start >= len {
+        Some(Err(()))
+    } else {
+        Some(Ok(start..=end.min(len - 1)))
+    }
+}
+
+/// Gets a file in the unpacked HTML bundle of a resource.
+///
+/// Bundles are served under a sandboxed path prefix, so screens could
+/// load them in frames, with paths relative to the entry file.
+///
+/// # Authorization
+///
+/// The request needs no authorization, as frames could not send
+/// the auth header, so only bundles used by approved posts are served.
+///
+/// # Response
+///
+/// The response body is the raw bytes of the file, with a strict
+/// `Content-Security-Policy` of [`bundle::CSP`].
+///
+/// # Errors
+///
+/// - [`Error::ResourceNotFound`] if the resource with the given id does not exist,
+/// is not an HTML bundle or is not used by any approved post.
+/// - [`Error::BundleFileNotFound`] if the file does not exist in the bundle.
+pub async fn get_bundle_file<Io: IoHandle>(
+    Path((Id(id), path)): Path<(Id, String)>,
+    State(Global {
+        worlds,
+        resource_store,
+        ..
+    }): State<Global<Io>>,
+) -> Result<Response, Error> {
+    let select = sd!(worlds.resource, id).and(1, 1..);
+    let lazy = gd!(select, id).ok_or(Error::ResourceNotFound(id))?;
+    let resource = lazy.get().await?;
+    let Variant::Html { files, .. } = resource.variant() else {
+        return Err(Error::ResourceNotFound(id));
+    };
+    if !is_used_by_approved(&worlds, resource.posts()).await {
+        return Err(Error::ResourceNotFound(id));
+    }
+    if !files.contains(&path) {
+        return Err(Error::BundleFileNotFound(path));
+    }
+
+    let stream = resource_store
+        .get(&Resource::bundle_file_name_of(resource.hash(), &path))
+        .await
+        .map_err(|_| Error::BundleFileNotFound(path.clone()))?;
+    Ok((
+        [
+            (header::CONTENT_TYPE, bundle::mime_of(&path)),
+            (header::CONTENT_SECURITY_POLICY, bundle::CSP),
+            (header::X_CONTENT_TYPE_OPTIONS, "nosniff"),
+        ],
+        Body::from_stream(stream),
+    )
+        .into_response())
+}
+
+/// Whether any of the posts with given ids is approved.
+async fn is_used_by_approved<Io: IoHandle>(worlds: &Worlds<Io>, posts: &[u64]) -> bool {
+    let Some(first) = posts.first().copied() else {
+        return false;
+    };
+    let mut select = worlds.post.select(0, first).hints(posts.iter().copied());
+    for id in &posts[1..] {
+        select = select.plus(0, *id);
+    }
+    let mut iter = select.iter();
+    while let Some(Ok(lazy)) = iter.next().await {
+        if posts.contains(&lazy.id())
+            && lazy
+                .get()
+                .await
+                .is_ok_and(|post| post.state().status() == Status::Approved)
+        {
+            return true;
+        }
+    }
+    false
+}
+
+/// Request URL query parameters for [`get_thumbnail`].
+///
+/// # Examples
+///
+/// ```json
+/// {
+///     "size": 256,
+/// }
+/// ```
+#[derive(Deserialize)]
+pub struct GetThumbnailParams {
+    /// Requested size of the thumbnail, as pixels of the longer edge.\
+    /// The smallest available size not less than it will be served.
+    /// The field can be omitted.
+    #[serde(default)]
+    pub size: Option<u32>,
+}
+
+/// Gets thumbnail of a resource.
+///
+/// Thumbnails are generated for images and PDF documents
+/// after they are uploaded, so they may not be available
+/// right after uploading.
+///
+/// # Request
+///
+/// The request **query parameters** is declared as [`GetThumbnailParams`].
+///
+/// # Authorization
+///
+/// The request must be authorized with [`Permission::GetPubPost`].
+///
+/// # Response
+///
+/// The response body is the JPEG-encoded thumbnail.
+///
+/// # Errors
+///
+/// - [`Error::ResourceNotFound`] if the resource with the given id does not exist.
+/// - [`Error::ThumbnailNotFound`] if the thumbnail is not available.
+/// - [`Error::PermissionDenied`] if the resource is not blocked **and** is not owned by the authorized account.
+pub async fn get_thumbnail<Io: IoHandle>(
+    Path(Id(id)): Path<Id>,
+    Query(GetThumbnailParams { size }): Query<GetThumbnailParams>,
+    auth: Auth,
+    State(Global {
+        worlds,
+        resource_store,
+        ..
+    }): State<Global<Io>>,
+) -> Result<Response, Error> {
+    let select = sd!(worlds.account, auth.account);
+    va!(auth, select => GetPubPost);
+    let select = sd!(worlds.resource, id);
+    let lazy = gd!(select, id).ok_or(Error::ResourceNotFound(id))?;
+    let resource = lazy.get().await?;
+    if resource.owner() != Id(auth.account) && !resource.is_blocked() {
+        return Err(Error::PermissionDenied);
+    }
+
+    let size = thumbnail::fit_size(size.unwrap_or(thumbnail::DEFAULT_SIZE));
+    let stream = resource_store
+        .get(&resource.thumbnail_name(size))
+        .await
+        .map_err(|_| Error::ThumbnailNotFound(id))?;
+    Ok((
+        [(header::CONTENT_TYPE, "image/jpeg")],
+        Body::from_stream(stream),
+    )
+        .into_response())
+}
+
+/// Information of a resource.
+///
+/// Contents of resources stored inline, like [`Variant::Text`],
+/// are included in the variant.
+///
+/// Videos in codecs screens could not decode are flagged
+/// with `playable` being `false`.
+///
+/// # Examples
+///
+/// ```json
+/// {
+///     "variant": {
+///         "type": "Image",
+///         "duration": 10,
+///     },
+///     "size": 102400,
+///     "format": "Jpeg",
+///     "image": {
+///         "width": 1920,
+///         "height": 1080,
+///         "orientation": 1,
+///     },
+/// }
+/// ```
+#[derive(Serialize)]
+pub struct Info {
+    /// The resource variant.
+    pub variant: Variant,
+    /// Metadata of the payload.
+    #[serde(flatten)]
+    pub metadata: Metadata,
+}
+
+impl From<&Resource> for Info {
+    #[inline]
+    fn from(resource: &Resource) -> Self {
+        Self {
+            variant: resource.variant().clone(),
+            metadata: resource.metadata().clone(),
+        }
+    }
+}
+
+pub async fn get_info<Io: IoHandle>(
+    Path(Id(id)): Path<Id>,
+    auth: Auth,
+    State(Global { worlds, .. }): State<Global<Io>>,
+) -> Result<Json<Info>, Error> {
+    let select = sd!(worlds.account, auth.account);
+    va!(auth, select => GetPubPost);
+    let select = sd!(worlds.resource, id).and(1, 1..);
+    let lazy = gd!(select, id).ok_or(Error::ResourceNotFound(id))?;
+    let resource = lazy.get().await?;
+    if resource.owner() != Id(auth.account) && !resource.is_blocked() {
+        return Err(Error::PermissionDenied);
+    }
+    Ok(Json(Info::from(&*resource)))
+}
+
+/// Request body for [`bulk_get_info`].
+#[derive(Deserialize)]
+pub struct BulkGetInfoReq {
+    /// Ids of the resources.
+    pub ids: Box<[Id]>,
+}
+
+pub async fn bulk_get_info<Io: IoHandle>(
+    auth: Auth,
+    State(Global { worlds, .. }): State<Global<Io>>,
+    Json(BulkGetInfoReq { ids }): Json<BulkGetInfoReq>,
+) -> Result<Json<HashMap<u64, Info>>, Error> {
+    let select = sd!(worlds.account, auth.account);
+    va!(auth, select => GetPubPost);
 
     let mut infos = HashMap::with_capacity(ids.len());
     let mut select = worlds
         .resource
-        .select(1, 1)
+        .select(1, 1..)
         .hints(ids.iter().copied().map(From::from));
     for &id in &*ids {
         select = select.and(0, id.0);
@@ -269,14 +1547,274 @@ pub async fn bulk_get_info<Io: IoHandle>(
                 if resource.owner() != Id(auth.account) && !resource.is_blocked() {
                     return Err(Error::PermissionDenied);
                 }
-                infos.insert(
-                    resource.id(),
-                    Info {
-                        variant: resource.variant().clone(),
-                    },
-                );
+                infos.insert(resource.id(), Info::from(&*resource));
             }
         }
     }
     Ok(Json(infos))
 }
+
+/// Response body for [`get_posts`].
+///
+/// # Examples
+///
+/// ```json
+/// {
+///     "posts": ["12", "13"],
+/// }
+/// ```
+#[derive(Serialize)]
+pub struct GetPostsRes {
+    /// Ids of posts using the resource.
+    pub posts: Box<[Id]>,
+}
+
+/// Gets ids of posts using a resource.
+///
+/// # Authorization
+///
+/// The request must be authorized by the owner of the resource,
+/// or with [`Permission::ReviewPost`].
+///
+/// # Response
+///
+/// The response body is declared as [`GetPostsRes`].
+pub async fn get_posts<Io: IoHandle>(
+    Path(Id(id)): Path<Id>,
+    auth: Auth,
+    State(Global { worlds, .. }): State<Global<Io>>,
+) -> Result<Json<GetPostsRes>, Error> {
+    let select = sd!(worlds.account, auth.account);
+    let this_lazy = va!(auth, select);
+    let permitted_review = this_lazy
+        .get()
+        .await?
+        .tags()
+        .contains_permission(&Tag::Permission(Permission::ReviewPost));
+    let select = sd!(worlds.resource, id);
+    let lazy = gd!(select, id).ok_or(Error::ResourceNotFound(id))?;
+    let resource = lazy.get().await?;
+    if resource.owner() != Id(auth.account) && !permitted_review {
+        return Err(Error::PermissionDenied);
+    }
+    Ok(Json(GetPostsRes {
+        posts: resource.posts().iter().copied().map(Id).collect(),
+    }))
+}
+
+/// Brief information of a resource owned by the authorized account.
+///
+/// # Examples
+///
+/// ```json
+/// {
+///     "id": "1234567890",
+///     "variant": {
+///         "type": "Video",
+///         "duration": 60,
+///     },
+///     "size": 10485760,
+///     "used": false,
+/// }
+/// ```
+#[derive(Serialize)]
+pub struct MineInfo {
+    /// Id of the resource.
+    pub id: Id,
+    /// The resource variant.
+    pub variant: Variant,
+    /// Size of the payload, as bytes.
+    pub size: u64,
+    /// Whether the resource is used by any post.
+    pub used: bool,
+}
+
+/// Response body for [`list_mine`].
+#[derive(Serialize)]
+pub struct ListMineRes {
+    /// Resources owned by the authorized account.
+    pub resources: Vec<MineInfo>,
+}
+
+/// Lists resources owned by the authorized account.
+///
+/// # Response
+///
+/// The response body is declared as [`ListMineRes`].
+pub async fn list_mine<Io: IoHandle>(
+    auth: Auth,
+    State(Global { worlds, .. }): State<Global<Io>>,
+) -> Result<Json<ListMineRes>, Error> {
+    let select = sd!(worlds.account, auth.account);
+    va!(auth, select);
+
+    let mut resources = vec![];
+    let select = worlds.resource.select(3, auth.account);
+    let mut iter = select.iter();
+    while let Some(Ok(lazy)) = iter.next().await {
+        if let Ok(resource) = lazy.get().await {
+            if resource.owner() == Id(auth.account) {
+                resources.push(MineInfo {
+                    id: Id(resource.id()),
+                    variant: resource.variant().clone(),
+                    size: resource.metadata().size,
+                    used: resource.is_blocked(),
+                });
+            }
+        }
+    }
+    Ok(Json(ListMineRes { resources }))
+}
+
+/// Deletes a resource that is not used by any post.
+///
+/// The storage usage is released, and the file is removed
+/// if no other resource refers to it.
+///
+/// # Authorization
+///
+/// The request must be authorized by the owner of the resource.
+///
+/// # Errors
+///
+/// - [`Error::ResourceNotFound`] if the resource with the given id does not exist.
+/// - [`Error::PermissionDenied`] if the resource is not owned by the authorized account.
+/// - [`Error::ResourceUsed`] if the resource is used by some post.
+pub async fn remove<Io: IoHandle>(
+    Path(Id(id)): Path<Id>,
+    auth: Auth,
+    State(Global {
+        worlds,
+        resource_store,
+        resource_locks,
+        ..
+    }): State<Global<Io>>,
+) -> Result<(), Error> {
+    let select = sd!(worlds.account, auth.account);
+    va!(auth, select);
+
+    let select = sd!(worlds.resource, id);
+    let mut lazy = gd!(select, id).ok_or(Error::ResourceNotFound(id))?;
+    // Checked mutably, so the resource could not be
+    // used by a post before being destroyed.
+    let resource = lazy.get_mut().await?;
+    if resource.owner() != Id(auth.account) {
+        return Err(Error::PermissionDenied);
+    }
+    if resource.is_blocked() {
+        return Err(Error::ResourceUsed(id));
+    }
+    let released = Released::from(&*resource);
+    lazy.destroy().await?;
+    release(&worlds, &*resource_store, &resource_locks, vec![released]).await;
+    Ok(())
+}
+
+/// Gets files that would be removed by the next garbage collection,
+/// without removing them.
+///
+/// # Authorization
+///
+/// The request must be authorized with [`Permission::Maintain`].
+///
+/// # Response
+///
+/// The response body is declared as [`GarbageReport`].
+pub async fn get_garbage<Io: IoHandle>(
+    auth: Auth,
+    State(global): State<Global<Io>>,
+) -> Result<Json<GarbageReport>, Error> {
+    let select = sd!(global.worlds.account, auth.account);
+    va!(auth, select => Maintain);
+    crate::job::collect_garbage(&global, true).await.map(Json)
+}
+
+/// Rehashes every payload file in the resource store, and marks
+/// posts using resources with missing or corrupted files.
+///
+/// This may take a long time with large stores.
+///
+/// # Authorization
+///
+/// The request must be authorized with [`Permission::Maintain`].
+///
+/// # Response
+///
+/// The response body is declared as [`ScrubReport`].
+pub async fn scrub<Io: IoHandle>(
+    auth: Auth,
+    State(global): State<Global<Io>>,
+) -> Result<Json<ScrubReport>, Error> {
+    let select = sd!(global.worlds.account, auth.account);
+    va!(auth, select => Maintain);
+    crate::job::scrub(&global, false).await.map(Json)
+}
+
+/// Storage usage of an owner.
+#[derive(Serialize)]
+pub struct OwnerUsage {
+    /// Used bytes.
+    pub used: u64,
+    /// Quota of the owner, as bytes.\
+    /// This is `null` if the usage is unlimited.
+    pub quota: Option<u64>,
+}
+
+/// Response body for [`get_usage`].
+///
+/// # Examples
+///
+/// ```json
+/// {
+///     "account": {
+///         "used": 10485760,
+///         "quota": 524288000,
+///     },
+///     "departments": {
+///         "Office": {
+///             "used": 104857600,
+///             "quota": null,
+///         },
+///     },
+/// }
+/// ```
+#[derive(Serialize)]
+pub struct UsageRes {
+    /// Storage usage of the account.
+    pub account: OwnerUsage,
+    /// Storage usages of departments of the account.
+    pub departments: HashMap<String, OwnerUsage>,
+}
+
+/// Gets storage usages and quotas of the authorized account.
+///
+/// # Response
+///
+/// The response body is declared as [`UsageRes`].
+pub async fn get_usage<Io: IoHandle>(
+    auth: Auth,
+    State(Global { worlds, config, .. }): State<Global<Io>>,
+) -> Result<Json<UsageRes>, Error> {
+    let select = sd!(worlds.account, auth.account);
+    let lazy = va!(auth, select);
+    let owners = UsageOwner::of(lazy.get().await?);
+
+    let mut account = None;
+    let mut departments = HashMap::new();
+    for (owner, used) in owners.iter().zip(usages(&worlds, &owners).await?) {
+        let usage = OwnerUsage {
+            used,
+            quota: config.quota.of(owner),
+        };
+        match owner {
+            UsageOwner::Account(_) => account = Some(usage),
+            UsageOwner::Department(department) => {
+                departments.insert(department.to_owned(), usage);
+            }
+        }
+    }
+    Ok(Json(UsageRes {
+        account: account.ok_or(Error::Unknown)?,
+        departments,
+    }))
+}
@@ -0,0 +1,211 @@
+use std::collections::{HashMap, HashSet};
+
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use dmds::{IoHandle, StreamExt};
+use serde::{Deserialize, Serialize};
+use sms4_backend::{
+    account::{Tag, TagEntry},
+    post::{Priority, Status},
+    resource::store::ResourceStore,
+    Error, Id,
+};
+use time::{Date, OffsetDateTime};
+
+#[allow(unused_imports)]
+use sms4_backend::account::Permission;
+
+use crate::{Auth, Global};
+
+/// Request URL query parameters for getting statistics.
+///
+/// # Examples
+///
+/// ```json
+/// {
+///     "after": "2024-01-01",
+///     "before": "2024-01-31",
+/// }
+/// ```
+#[derive(Deserialize)]
+pub struct StatsParams {
+    /// Count posts created from this date.\
+    /// The field can be omitted.
+    #[serde(default)]
+    pub after: Option<Date>,
+    /// Count posts created until this date.\
+    /// The field can be omitted.
+    #[serde(default)]
+    pub before: Option<Date>,
+}
+
+/// Statistics of posts.
+#[derive(Serialize, Default)]
+pub struct PostStats {
+    /// Number of posts.
+    pub total: usize,
+    /// Post counts by current status.
+    pub by_status: HashMap<Status, usize>,
+    /// Post counts by priority.
+    pub by_priority: HashMap<Priority, usize>,
+    /// Post counts by creator.
+    pub by_creator: HashMap<Id, usize>,
+    /// Post counts by departments of the creators.
+    ///
+    /// A post is counted once for each department
+    /// of its creator.
+    pub by_department: HashMap<String, usize>,
+    /// Average duration from creation to the first
+    /// review result of a post, as seconds.
+    ///
+    /// This is `null` if there is no reviewed post.
+    pub avg_review_turnaround: Option<i64>,
+}
+
+/// Storage usage of a resource variant.
+#[derive(Serialize, Default)]
+pub struct ResourceUsage {
+    /// Number of resources.
+    pub count: usize,
+    /// Total size of resource files, as bytes.
+    ///
+    /// A file shared by multiple resources is counted once.
+    pub bytes: u64,
+}
+
+/// Response body for getting statistics.
+#[derive(Serialize)]
+pub struct StatsRes {
+    /// Statistics of posts.
+    pub posts: PostStats,
+    /// Resource storage usage by variant type.
+    pub resources: HashMap<&'static str, ResourceUsage>,
+    /// Number of active notifications.
+    ///
+    /// Notifications have no end time, so a notification
+    /// is active since its start time, and is displayed
+    /// by the screens since then.
+    pub active_notifications: usize,
+}
+
+/// Gets administrative statistics.
+///
+/// # Request
+///
+/// The request **query parameters** is declared as [`StatsParams`].
+///
+/// # Authorization
+///
+/// The request must be authorized with [`Permission::Maintain`].
+///
+/// # Response
+///
+/// The response body is declared as [`StatsRes`].
+pub async fn get<Io: IoHandle>(
+    Query(StatsParams { after, before }): Query<StatsParams>,
+    auth: Auth,
+    State(Global {
+        worlds,
+        resource_store,
+        ..
+    }): State<Global<Io>>,
+) -> Result<Json<StatsRes>, Error> {
+    let select = sd!(worlds.account, auth.account);
+    va!(auth, select => Maintain);
+
+    let mut posts = PostStats::default();
+    let mut turnaround_sum = 0_i64;
+    let mut reviewed = 0_i64;
+    let select = worlds.post.select_all();
+    let mut iter = select.iter();
+    while let Some(Ok(lazy)) = iter.next().await {
+        let Ok(post) = lazy.get().await else {
+            continue;
+        };
+        let created = post.states()[0].time();
+        if after.is_some_and(|d| created.date() < d) || before.is_some_and(|d| created.date() > d) {
+            continue;
+        }
+
+        posts.total += 1;
+        *posts.by_status.entry(post.state().status()).or_default() += 1;
+        *posts.by_priority.entry(post.priority()).or_default() += 1;
+        *posts.by_creator.entry(post.creator()).or_default() += 1;
+        if let Some(state) = post.states()[1..].iter().find(|s| {
+            matches!(s.status(), Status::Approved | Status::Rejected)
+                && s.operator() != sms4_backend::post::State::SYSTEM_OPERATOR
+        }) {
+            turnaround_sum += (state.time() - created).whole_seconds();
+            reviewed += 1;
+        }
+    }
+    if reviewed > 0 {
+        posts.avg_review_turnaround = Some(turnaround_sum / reviewed);
+    }
+
+    if let Some(first) = posts.by_creator.keys().next().copied() {
+        let mut select = worlds.account.select(0, first.0);
+        for id in posts.by_creator.keys() {
+            select = select.plus(0, id.0);
+        }
+        select = select.hints(posts.by_creator.keys().copied().map(From::from));
+        let mut iter = select.iter();
+        while let Some(Ok(lazy)) = iter.next().await {
+            let Some(count) = posts.by_creator.get(&Id(lazy.id())).copied() else {
+                continue;
+            };
+            if let Ok(account) = lazy.get().await {
+                for tag in account
+                    .tags()
+                    .from_entry(&TagEntry::Department)
+                    .into_iter()
+                    .flatten()
+                {
+                    if let Tag::Department(department) = tag {
+                        *posts
+                            .by_department
+                            .entry(department.to_owned())
+                            .or_default() += count;
+                    }
+                }
+            }
+        }
+    }
+
+    let mut resources: HashMap<&'static str, ResourceUsage> = HashMap::new();
+    let mut counted_files = HashSet::new();
+    let select = worlds.resource.select_all();
+    let mut iter = select.iter();
+    while let Some(Ok(lazy)) = iter.next().await {
+        if let Ok(resource) = lazy.get().await {
+            let usage = resources.entry(resource.variant().type_name()).or_default();
+            usage.count += 1;
+            if !resource.variant().has_payload() || !counted_files.insert(resource.hash()) {
+                // Inline resources have no files, and files are
+                // shared between resources with the same content.
+                continue;
+            }
+            if let Ok(meta) = resource_store.head(&resource.file_name()).await {
+                usage.bytes += meta.len;
+            }
+        }
+    }
+
+    let now = OffsetDateTime::now_utc();
+    let mut active_notifications = 0;
+    let select = worlds.notification.select_all();
+    let mut iter = select.iter();
+    while let Some(Ok(lazy)) = iter.next().await {
+        if lazy.get().await.is_ok_and(|n| n.time() <= now) {
+            active_notifications += 1;
+        }
+    }
+
+    Ok(Json(StatsRes {
+        posts,
+        resources,
+        active_notifications,
+    }))
+}
@@ -0,0 +1,21 @@
+//! HTTP date formatting and parsing.
+
+use time::{OffsetDateTime, PrimitiveDateTime, UtcOffset};
+
+/// Format of HTTP dates, as the IMF-fixdate of RFC 9110.
+const HTTP_DATE: &str =
+    "[weekday repr:short], [day] [month repr:short] [year] [hour]:[minute]:[second] GMT";
+
+/// Formats a time as an HTTP date.
+pub fn format(time: OffsetDateTime) -> Option<String> {
+    time.to_offset(UtcOffset::UTC)
+        .format(&time::format_description::parse(HTTP_DATE).ok()?)
+        .ok()
+}
+
+/// Parses an HTTP date.
+pub fn parse(value: &str) -> Option<OffsetDateTime> {
+    PrimitiveDateTime::parse(value, &time::format_description::parse(HTTP_DATE).ok()?)
+        .ok()
+        .map(PrimitiveDateTime::assume_utc)
+}
@@ -0,0 +1,563 @@
+//! Background jobs running next to the dmds daemons.
+
+use std::{
+    collections::{HashMap, HashSet},
+    time::SystemTime,
+};
+
+use dmds::{IoHandle, StreamExt};
+use serde::Serialize;
+use sms4_backend::{
+    account::{Permission, Tag},
+    post::{State, Status},
+    resource::{
+        store::{LocalFs, ResourceStore},
+        FileName, Resource,
+    },
+    Error, Id,
+};
+use time::OffsetDateTime;
+
+use crate::{gd, sd, Global};
+
+/// Runs the given job periodically with given interval, forever.
+///
+/// Errors of a single run are logged and won't stop the job.
+macro_rules! periodic {
+    ($name:literal, $interval:expr, $job:expr) => {{
+        let mut interval = tokio::time::interval($interval);
+        loop {
+            interval.tick().await;
+            if let Err(err) = $job.await {
+                tracing::error!("error running job {}: {err}", $name);
+            }
+        }
+    }};
+}
+
+/// Interval between two runs of [`expire_pending_posts`].
+pub const EXPIRE_PENDING_POSTS_INTERVAL: std::time::Duration =
+    std::time::Duration::from_secs(60 * 60);
+
+/// Runs [`expire_pending_posts`] periodically.
+pub async fn expire_pending_posts_daemon<Io: IoHandle>(
+    global: Global<Io>,
+    interval: std::time::Duration,
+) {
+    periodic!(
+        "expire pending posts",
+        interval,
+        expire_pending_posts(&global)
+    )
+}
+
+/// Rejects pending posts whose start date has passed,
+/// and notifies their creators by email.
+///
+/// Returns the number of expired posts.
+pub async fn expire_pending_posts<Io: IoHandle>(
+    Global {
+        worlds,
+        config,
+        smtp_transport,
+        ..
+    }: &Global<Io>,
+) -> Result<usize, Error> {
+    let today = OffsetDateTime::now_utc().date();
+    let select = worlds.post.select_all().and(3, 0);
+    let mut iter = select.iter();
+    let mut expired: HashMap<Id, Vec<String>> = HashMap::new();
+    while let Some(Ok(mut lazy)) = iter.next().await {
+        if !lazy.get().await.is_ok_and(|post| {
+            post.state().status() == Status::Pending && *post.time().start() < today
+        }) {
+            continue;
+        }
+        let post = lazy.get_mut().await?;
+        post.pust_state(State::system(
+            Status::Rejected,
+            "automatically rejected: not reviewed before the start date".to_owned(),
+        ))?;
+        expired
+            .entry(post.creator())
+            .or_default()
+            .push(post.title().to_owned());
+        lazy.close().await?;
+    }
+
+    let count: usize = expired.values().map(Vec::len).sum();
+    for (creator, titles) in expired {
+        let select = sd!(worlds.account, creator.0);
+        let Some(lazy) = gd!(select, creator.0) else {
+            continue;
+        };
+        let account = lazy.get().await?;
+        let body = titles.iter().fold(
+            "The following posts were rejected automatically because they \
+            were not reviewed before their start dates:\n"
+                .to_owned(),
+            |body, title| body + "\n- " + title,
+        );
+        if let Err(err) = account
+            .send_notice(
+                &config.smtp,
+                smtp_transport,
+                "Your posts on SubIT Screen Management System have expired",
+                body,
+            )
+            .await
+        {
+            tracing::error!(
+                "failed to notify account {} about expired posts: {err}",
+                creator.0
+            );
+        }
+    }
+    if count > 0 {
+        tracing::info!("{count} pending posts expired");
+    }
+    Ok(count)
+}
+
+/// Interval between two runs of [`collect_garbage`].
+pub const COLLECT_GARBAGE_INTERVAL: std::time::Duration =
+    std::time::Duration::from_secs(6 * 60 * 60);
+
+/// Files modified within this duration are never collected,
+/// as they may belong to uploads in progress.
+const GARBAGE_GRACE: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+/// Report of a garbage collection.
+#[derive(Debug, Default, Serialize)]
+pub struct GarbageReport {
+    /// Names of payload and thumbnail files no resource refers to.
+    pub orphans: Vec<String>,
+    /// Names of upload buffers without alive sessions.
+    pub stale_buffers: Vec<String>,
+    /// Total size of the files, as bytes.
+    pub bytes: u64,
+}
+
+/// Runs [`collect_garbage`] periodically.
+pub async fn collect_garbage_daemon<Io: IoHandle>(
+    global: Global<Io>,
+    interval: std::time::Duration,
+) {
+    periodic!("collect garbage", interval, collect_garbage(&global, false))
+}
+
+/// Removes files in the resource store that no resource
+/// refers to, and upload buffers of expired sessions.
+///
+/// Nothing will be removed if `dry_run` is `true`, and the
+/// report lists files that would be removed.
+pub async fn collect_garbage<Io: IoHandle>(
+    global: &Global<Io>,
+    dry_run: bool,
+) -> Result<GarbageReport, Error> {
+    collect_garbage_at(global, dry_run, SystemTime::now()).await
+}
+
+/// Runs [`collect_garbage`] as if it were the given time,
+/// which decides whether files are out of [`GARBAGE_GRACE`].
+pub(crate) async fn collect_garbage_at<Io: IoHandle>(
+    Global {
+        worlds,
+        config,
+        resource_sessions,
+        resource_store,
+        resource_locks,
+        ..
+    }: &Global<Io>,
+    dry_run: bool,
+    now: SystemTime,
+) -> Result<GarbageReport, Error> {
+    // List files before scanning records, as records are
+    // inserted before their files are moved in.
+    let stored = resource_store.list().await.map_err(|err| {
+        tracing::error!("failed to list resource store: {err}");
+        Error::Unknown
+    })?;
+    let buffers = LocalFs::new(config.resource_path.clone())
+        .list()
+        .await
+        .map_err(|_| Error::Unknown)?;
+    let mut candidates = vec![];
+    for (meta, in_store) in stored
+        .into_iter()
+        .map(|meta| (meta, true))
+        .chain(buffers.into_iter().map(|meta| (meta, false)))
+    {
+        let recent = meta
+            .modified
+            .and_then(|t| now.duration_since(t).ok())
+            .map_or(true, |age| age < GARBAGE_GRACE);
+        if recent {
+            continue;
+        }
+        // Buffers are always local, while other files are in the store.
+        match Resource::parse_file_name(&meta.name) {
+            Some(file) if in_store != matches!(file, FileName::Buf(_)) => {
+                candidates.push((meta.name, file, meta.len))
+            }
+            _ => {}
+        }
+    }
+    if candidates.is_empty() {
+        return Ok(GarbageReport::default());
+    }
+
+    // Errors abort the collection, or referred files may be removed.
+    //
+    // Records are scanned without the files lock so uploads
+    // won't wait for the scan. Resources inserted in the meantime
+    // are found by looking up the remaining hashes with the lock held.
+    let mut hashes = HashSet::new();
+    let select = worlds.resource.select_all();
+    let mut iter = select.iter();
+    while let Some(lazy) = iter.next().await {
+        hashes.insert(lazy?.get().await?.hash());
+    }
+    candidates.retain(|(_, file, _)| file_hash(*file).map_or(true, |hash| !hashes.contains(&hash)));
+
+    let _files_lock = resource_locks.files.lock().await;
+    let mut referred = HashMap::new();
+    for hash in candidates
+        .iter()
+        .filter_map(|(_, file, _)| file_hash(*file))
+    {
+        if referred.contains_key(&hash) {
+            continue;
+        }
+        let select = worlds.resource.select(2, hash);
+        let mut iter = select.iter();
+        let mut found = false;
+        while let Some(lazy) = iter.next().await {
+            if lazy?.get().await?.hash() == hash {
+                found = true;
+                break;
+            }
+        }
+        referred.insert(hash, found);
+    }
+    let garbage: Vec<_> = {
+        let mut sessions = resource_sessions.lock().await;
+        candidates
+            .into_iter()
+            .filter(|(_, file, _)| match *file {
+                FileName::Buf(id) => !sessions.contains(id),
+                file => file_hash(file).is_some_and(|hash| !referred[&hash]),
+            })
+            .collect()
+    };
+
+    let mut report = GarbageReport::default();
+    for (name, file, len) in garbage {
+        if !dry_run {
+            let result = if matches!(file, FileName::Buf(_)) {
+                tokio::fs::remove_file(config.resource_path.join(&name)).await
+            } else {
+                resource_store.delete(&name).await
+            };
+            if let Err(err) = result {
+                tracing::error!("failed to remove garbage file {name}: {err}");
+                continue;
+            }
+            tracing::info!("removed garbage file {name}");
+        }
+        report.bytes += len;
+        if matches!(file, FileName::Buf(_)) {
+            report.stale_buffers.push(name);
+        } else {
+            report.orphans.push(name);
+        }
+    }
+    if !dry_run && report.bytes > 0 {
+        tracing::info!(
+            "collected {} garbage files of {} bytes",
+            report.orphans.len() + report.stale_buffers.len(),
+            report.bytes
+        );
+    }
+    Ok(report)
+}
+
+/// Gets the content hash of a file, or `None` for upload buffers.
+fn file_hash(file: FileName) -> Option<u64> {
+    match file {
+        FileName::Payload(hash) | FileName::Thumbnail { hash, .. } | FileName::Bundle(hash) => {
+            Some(hash)
+        }
+        FileName::Buf(_) => None,
+    }
+}
+
+/// Interval between two runs of [`scrub`].
+pub const SCRUB_INTERVAL: std::time::Duration = std::time::Duration::from_secs(24 * 60 * 60);
+
+/// Report of a storage scrub.
+#[derive(Debug, Default, Serialize)]
+pub struct ScrubReport {
+    /// Number of checked payload files.
+    pub checked: usize,
+    /// Names of payload files missing from the store.
+    pub missing: Vec<String>,
+    /// Names of payload files whose contents don't match
+    /// what was uploaded.
+    pub corrupted: Vec<String>,
+    /// Ids of resources with missing or corrupted payload files.
+    pub resources: Vec<Id>,
+    /// Ids of posts using these resources.
+    pub posts: Vec<Id>,
+    /// Ids of posts whose marks are cleared, as payload files
+    /// of their damaged resources match again.
+    pub cleared: Vec<Id>,
+}
+
+/// Runs [`scrub`] periodically, notifying maintainers.
+pub async fn scrub_daemon<Io: IoHandle>(global: Global<Io>, interval: std::time::Duration) {
+    periodic!("scrub resource store", interval, scrub(&global, true))
+}
+
+/// A payload file expected in the resource store.
+struct Expected {
+    /// Size of the file, as bytes.
+    size: u64,
+    /// Ids of resources referring to the file, and ids
+    /// of posts using each of them.
+    resources: Vec<(Id, Vec<u64>)>,
+}
+
+/// Rehashes a stored payload file, and returns whether
+/// it matches the expectation.
+async fn check_file(
+    store: &dyn ResourceStore,
+    hash: u64,
+    expected: &Expected,
+) -> std::io::Result<bool> {
+    let mut stream = store.get(&Resource::file_name_of(hash)).await?;
+    let mut hasher = highway::PortableHash::default();
+    let mut len = 0_u64;
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        len += chunk.len() as u64;
+        highway::HighwayHash::append(&mut hasher, &chunk);
+    }
+    Ok(len == expected.size && std::hash::Hasher::finish(&hasher) == hash)
+}
+
+/// Rehashes every payload file in the resource store, and reports
+/// files that are missing or don't match their content hashes.
+///
+/// Posts using affected resources are marked, so reviewers know.
+/// Marks of resources whose files match again, like restored
+/// from backups, are cleared.
+/// Accounts with [`Permission::Maintain`] are notified by email
+/// about affected files if `notify` is `true`, only once for each
+/// resource, when a post is first marked with it.
+pub async fn scrub<Io: IoHandle>(global: &Global<Io>, notify: bool) -> Result<ScrubReport, Error> {
+    let Global {
+        worlds,
+        resource_store,
+        ..
+    } = global;
+    let mut files: HashMap<u64, Expected> = HashMap::new();
+    let select = worlds.resource.select_all();
+    let mut iter = select.iter();
+    while let Some(lazy) = iter.next().await {
+        let lazy = lazy?;
+        let resource = lazy.get().await?;
+        if !resource.variant().has_payload() {
+            continue;
+        }
+        files
+            .entry(resource.hash())
+            .or_insert_with(|| Expected {
+                size: resource.metadata().size,
+                resources: vec![],
+            })
+            .resources
+            .push((Id(resource.id()), resource.posts().to_owned()));
+    }
+
+    let mut report = ScrubReport::default();
+    let mut damaged = vec![];
+    let mut intact = vec![];
+    let mut missing = vec![];
+    // Resource id => file name.
+    let mut names: HashMap<Id, String> = HashMap::new();
+    for (hash, expected) in files {
+        report.checked += 1;
+        match check_file(&**resource_store, hash, &expected).await {
+            Ok(true) => intact.extend(expected.resources),
+            Ok(false) => {
+                let name = Resource::file_name_of(hash);
+                names.extend(expected.resources.iter().map(|(id, _)| (*id, name.clone())));
+                report.corrupted.push(name);
+                damaged.extend(expected.resources);
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                missing.push((hash, expected.resources))
+            }
+            Err(err) => tracing::error!("failed to scrub {}: {err}", Resource::file_name_of(hash)),
+        }
+    }
+    // Files may be missing because of uploads and removals during
+    // the scrub, as records are inserted before files are moved in,
+    // and destroyed before files are removed.
+    for (hash, resources) in missing {
+        let name = Resource::file_name_of(hash);
+        if resource_store.head(&name).await.is_ok() {
+            continue;
+        }
+        let mut alive = vec![];
+        for (id, posts) in resources {
+            let select = sd!(worlds.resource, id.0);
+            if gd!(select, id.0).is_some() {
+                alive.push((id, posts));
+            }
+        }
+        if !alive.is_empty() {
+            names.extend(alive.iter().map(|(id, _)| (*id, name.clone())));
+            report.missing.push(name);
+            damaged.extend(alive);
+        }
+    }
+
+    let mut posts: HashMap<u64, Vec<Id>> = HashMap::new();
+    for (id, post_ids) in damaged {
+        report.resources.push(id);
+        for post in post_ids {
+            posts.entry(post).or_default().push(id);
+        }
+    }
+    // Resources marked by this scrub, and number of posts marking them.
+    let mut marked: HashMap<Id, usize> = HashMap::new();
+    for (id, resources) in posts {
+        let select = sd!(worlds.post, id);
+        let Some(mut lazy) = gd!(select, id) else {
+            continue;
+        };
+        let post = lazy.get_mut().await?;
+        let mut changed = false;
+        for resource in resources {
+            if post.mark_damaged(resource) {
+                *marked.entry(resource).or_default() += 1;
+                changed = true;
+            }
+        }
+        if changed {
+            lazy.close().await?;
+        }
+        report.posts.push(Id(id));
+    }
+
+    let mut posts: HashMap<u64, Vec<Id>> = HashMap::new();
+    for (id, post_ids) in intact {
+        for post in post_ids {
+            posts.entry(post).or_default().push(id);
+        }
+    }
+    for (id, resources) in posts {
+        let select = sd!(worlds.post, id);
+        let Some(mut lazy) = gd!(select, id) else {
+            continue;
+        };
+        if !lazy
+            .get()
+            .await?
+            .damaged_resources()
+            .iter()
+            .any(|r| resources.contains(r))
+        {
+            continue;
+        }
+        let post = lazy.get_mut().await?;
+        for resource in resources {
+            post.unmark_damaged(resource);
+        }
+        lazy.close().await?;
+        report.cleared.push(Id(id));
+    }
+
+    if report.missing.is_empty() && report.corrupted.is_empty() {
+        return Ok(report);
+    }
+    tracing::warn!(
+        "scrub found {} missing and {} corrupted resource files",
+        report.missing.len(),
+        report.corrupted.len()
+    );
+    // Resources marked by previous scrubs have been notified.
+    if notify && !marked.is_empty() {
+        let mut files: Vec<(&str, bool)> = marked
+            .keys()
+            .filter_map(|id| names.get(id))
+            .map(|name| (name.as_str(), report.missing.contains(name)))
+            .collect();
+        files.sort_unstable();
+        files.dedup();
+        notify_maintainers(global, &files, marked.len(), marked.values().sum()).await?;
+    }
+    Ok(report)
+}
+
+/// Notifies accounts with [`Permission::Maintain`] about damaged
+/// files found by [`scrub`], as file names and whether they are missing,
+/// with numbers of newly marked resources and marks on posts.
+async fn notify_maintainers<Io: IoHandle>(
+    Global {
+        worlds,
+        config,
+        smtp_transport,
+        ..
+    }: &Global<Io>,
+    files: &[(&str, bool)],
+    resources: usize,
+    marks: usize,
+) -> Result<(), Error> {
+    let body = files
+        .iter()
+        .map(|(name, missing)| {
+            format!(
+                "\n- {name} ({})",
+                if *missing { "missing" } else { "corrupted" }
+            )
+        })
+        .fold(
+            "The following resource files were found damaged by storage scrubbing:\n".to_owned(),
+            |body, line| body + &line,
+        )
+        + &format!(
+            "\n\n{resources} resources are affected, and they are marked on posts {marks} times."
+        );
+
+    let select = worlds.account.select_all();
+    let mut iter = select.iter();
+    while let Some(Ok(lazy)) = iter.next().await {
+        let Ok(account) = lazy.get().await else {
+            continue;
+        };
+        if !account
+            .tags()
+            .contains_permission(&Tag::Permission(Permission::Maintain))
+        {
+            continue;
+        }
+        if let Err(err) = account
+            .send_notice(
+                &config.smtp,
+                smtp_transport,
+                "Damaged resource files on SubIT Screen Management System",
+                body.clone(),
+            )
+            .await
+        {
+            tracing::error!(
+                "failed to notify account {} about damaged files: {err}",
+                lazy.id()
+            );
+        }
+    }
+    Ok(())
+}
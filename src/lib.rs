@@ -11,6 +11,7 @@ use serde::{Deserialize, Serialize};
 use time::Duration;
 
 pub mod config;
+pub mod http_date;
 
 pub mod account;
 pub mod notification;
@@ -60,6 +61,10 @@ pub enum Error {
 
     #[error("resource upload session {0} not found")]
     ResourceUploadSessionNotFound(u64),
+    #[error("resource upload session {0} is being written")]
+    ResourceUploadBusy(u64),
+    #[error("upload offset mismatch: expected at most {expected}")]
+    ResourceUploadOffsetMismatch { expected: u64 },
 
     #[error("not logged in")]
     NotLoggedIn,
@@ -72,15 +77,14 @@ pub enum Error {
     PostResourceEmpty,
     #[error("post with given post id {0} not found")]
     PostNotFound(u64),
-    #[error(
-        "post time range out of bound: given duration: {0}, expected: <= {}",
-        post::Post::MAX_DUR
-    )]
-    PostTimeRangeOutOfBound(Duration),
+    #[error("post time range out of bound: given duration: {given}, expected: <= {max}")]
+    PostTimeRangeOutOfBound { given: Duration, max: Duration },
     #[error("given post end time is earlier than now")]
     PostTimeEnded,
     #[error("invalid review result status")]
     InvalidPostStatus,
+    #[error("post category \"{0}\" not found")]
+    PostCategoryNotFound(String),
 
     #[error("resource {0} has already be used")]
     ResourceUsed(u64),
@@ -89,7 +93,25 @@ pub enum Error {
     #[error("resource {0} not found")]
     ResourceNotFound(u64),
     #[error("payload too large: max {max} bytes")]
-    PayloadTooLarge { max: usize },
+    PayloadTooLarge { max: u64 },
+    #[error("storage quota exceeded: {left} bytes left")]
+    QuotaExceeded { left: u64 },
+    #[error("resource payload does not match the declared variant")]
+    ResourceContentMismatch,
+    #[error("invalid resource variant: {0}")]
+    InvalidResourceVariant(&'static str),
+    #[error("invalid HTML bundle: {0}")]
+    InvalidHtmlBundle(&'static str),
+    #[error("file \"{0}\" not found in the HTML bundle")]
+    BundleFileNotFound(String),
+    #[error("PDF file has {actual} pages, but {declared} pages declared")]
+    PdfPagesMismatch { declared: u16, actual: u32 },
+    #[error("video lasts {actual} seconds, but {declared} seconds declared")]
+    VideoDurationMismatch { declared: u32, actual: u32 },
+    #[error("thumbnail of resource {0} not found")]
+    ThumbnailNotFound(u64),
+    #[error("resource {0} has no payload file")]
+    ResourceNoPayload(u64),
 
     #[error("notification {0} not found")]
     NotificationNotFound(u64),
@@ -110,13 +132,25 @@ impl Error {
             | Error::AccountNotFound
             | Error::UnverifiedAccountNotFound
             | Error::ResourceNotFound(_)
+            | Error::ThumbnailNotFound(_)
+            | Error::ResourceNoPayload(_)
+            | Error::BundleFileNotFound(_)
             | Error::NotificationNotFound(_) => StatusCode::NOT_FOUND,
             Error::ReqTooFrequent(_) => StatusCode::TOO_MANY_REQUESTS,
             Error::EmailAddress(_) => StatusCode::BAD_REQUEST,
             Error::Lettre(_) | Error::Smtp(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Error::NotLoggedIn => StatusCode::UNAUTHORIZED,
             Error::HeaderNonAscii(_) | Error::InvalidAuthHeader => StatusCode::BAD_REQUEST,
-            Error::ResourceUsed(_) => StatusCode::CONFLICT,
+            Error::InvalidResourceVariant(_)
+            | Error::PostCategoryNotFound(_)
+            | Error::InvalidHtmlBundle(_)
+            | Error::PdfPagesMismatch { .. }
+            | Error::VideoDurationMismatch { .. } => StatusCode::BAD_REQUEST,
+            Error::ResourceUsed(_)
+            | Error::ResourceUploadBusy(_)
+            | Error::ResourceUploadOffsetMismatch { .. } => StatusCode::CONFLICT,
+            Error::ResourceContentMismatch => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            Error::PayloadTooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
             Error::Database(_) | Error::Unknown | Error::ResourceSaveFailed => {
                 StatusCode::INTERNAL_SERVER_ERROR
             }
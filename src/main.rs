@@ -38,12 +38,15 @@ async fn main() {
                 world!(FsHandle::new(dpath!("posts"),false),ipc!(16)=> ..,368/4=> ..=367,ipc!(16)=> ..,1=> ..2),
             ),
             resource: Arc::new(
-                world!(FsHandle::new(dpath!("resources"),false),ipc!(256)=> ..,1=> ..2),
+                world!(FsHandle::new(dpath!("resources"),false),ipc!(256)=> ..,1=> ..=255,ipc!(256)=> ..,ipc!(16)=> ..),
             ),
             notification: Arc::new(
                 world! {FsHandle::new(dpath!("notifications"),false),ipc!(32)=> ..,368/4=> ..=367},
             ),
+            usage: Arc::new(world!(FsHandle::new(dpath!("usages"),false),ipc!(16)=> ..,1=> ..2)),
         }),
+        resource_store: config.resource_store.build(&config.resource_path),
+        resource_locks: Default::default(),
         config: Arc::new(config),
         test_cx: Default::default(),
         resource_sessions: Arc::new(Mutex::new(sms4_backend::resource::UploadSessions::new())),
@@ -61,8 +64,19 @@ async fn main() {
         post => 120,
         resource => 60,
         notification => 120,
+        usage => 60,
     }
 
+    tokio::spawn(job::expire_pending_posts_daemon(
+        state.clone(),
+        job::EXPIRE_PENDING_POSTS_INTERVAL,
+    ));
+    tokio::spawn(job::collect_garbage_daemon(
+        state.clone(),
+        job::COLLECT_GARBAGE_INTERVAL,
+    ));
+    tokio::spawn(job::scrub_daemon(state.clone(), job::SCRUB_INTERVAL));
+
     let app: Router<()> = routing(axum::Router::new()).with_state(state);
     let addr = std::net::SocketAddr::from(([127, 0, 0, 1], 8080));
     axum::serve(TcpListener::bind(addr).await.unwrap(), app)
@@ -88,14 +102,27 @@ pub mod routes {
     pub const GET_POSTS: &str = "/post/bulk-get";
     pub const MODIFY_POST: &str = "/post/modify/:id";
     pub const REVIEW_POST: &str = "/post/review/:id";
+    pub const BULK_REVIEW_POST: &str = "/post/bulk-review";
     pub const DELETE_POST: &str = "/post/delete/:id";
     pub const BULK_DELETE_POST: &str = "/post/bulk-delete";
 
     pub const NEW_UPLOAD_SESSION: &str = "/resource/new-session";
+    pub const NEW_TEXT_RESOURCE: &str = "/resource/text";
     pub const UPLOAD_RESOURCE: &str = "/resource/upload/:id";
+    pub const UPLOAD_RESOURCE_CHUNK: &str = "/resource/upload-chunk/:id";
+    pub const GET_UPLOAD_STATUS: &str = "/resource/upload-status/:id";
+    pub const FINISH_UPLOAD: &str = "/resource/finish-upload/:id";
     pub const GET_RESOURCE_PAYLOAD: &str = "/resource/payload/:id";
+    pub const GET_RESOURCE_THUMBNAIL: &str = "/resource/thumbnail/:id";
+    pub const GET_BUNDLE_FILE: &str = "/sandbox/html/:id/*path";
     pub const GET_RESOURCE_INFO: &str = "/resource/get/:id";
     pub const BULK_GET_RESOURCE_INFO: &str = "/resource/bulk-get";
+    pub const GET_RESOURCE_POSTS: &str = "/resource/posts/:id";
+    pub const LIST_MY_RESOURCES: &str = "/resource/mine";
+    pub const DELETE_RESOURCE: &str = "/resource/delete/:id";
+    pub const GET_RESOURCE_GARBAGE: &str = "/resource/garbage";
+    pub const SCRUB_RESOURCES: &str = "/resource/scrub";
+    pub const GET_RESOURCE_USAGE: &str = "/resource/usage";
 
     pub const NOTIFY: &str = "/notification/new";
     pub const FILTER_NOTIFICATIONS: &str = "/notification/filter";
@@ -104,6 +131,8 @@ pub mod routes {
     pub const DELETE_NOTIFICATION: &str = "/notification/delete/:id";
     pub const BULK_DELETE_NOTIFICATION: &str = "/notification/bulk-delete";
     pub const MODIFY_NOTIFICATION: &str = "/notification/modify/:id";
+
+    pub const GET_STATS: &str = "/stats";
 }
 
 #[derive(Debug)]
@@ -111,6 +140,8 @@ pub struct Global<Io: IoHandle> {
     pub smtp_transport: Arc<AsyncSmtpTransport<lettre::Tokio1Executor>>,
     pub worlds: Arc<Worlds<Io>>,
     pub resource_sessions: Arc<Mutex<resource::UploadSessions>>,
+    pub resource_store: Arc<dyn resource::store::ResourceStore>,
+    pub resource_locks: Arc<handle::resource::ResourceLocks>,
     pub config: Arc<Config>,
 
     pub test_cx: Arc<sms4_backend::TestCx>,
@@ -125,6 +156,8 @@ impl<Io: IoHandle> Clone for Global<Io> {
             config: self.config.clone(),
             test_cx: self.test_cx.clone(),
             resource_sessions: self.resource_sessions.clone(),
+            resource_store: self.resource_store.clone(),
+            resource_locks: self.resource_locks.clone(),
         }
     }
 }
@@ -132,8 +165,9 @@ impl<Io: IoHandle> Clone for Global<Io> {
 type AccountWorld<Io> = World<Account, 1, Io>;
 type UnverifiedAccountWorld<Io> = World<sms4_backend::account::Unverified, 1, Io>;
 type PostWorld<Io> = World<sms4_backend::post::Post, 4, Io>;
-type ResourceWorld<Io> = World<sms4_backend::resource::Resource, 2, Io>;
+type ResourceWorld<Io> = World<sms4_backend::resource::Resource, 4, Io>;
 type NotificationWorld<Io> = World<sms4_backend::notification::Notification, 2, Io>;
+type UsageWorld<Io> = World<sms4_backend::resource::usage::Usage, 2, Io>;
 
 #[derive(Debug)]
 pub struct Worlds<Io: IoHandle> {
@@ -142,9 +176,11 @@ pub struct Worlds<Io: IoHandle> {
     post: Arc<PostWorld<Io>>,
     resource: Arc<ResourceWorld<Io>>,
     notification: Arc<NotificationWorld<Io>>,
+    usage: Arc<UsageWorld<Io>>,
 }
 
 mod handle;
+mod job;
 
 #[derive(Debug)]
 pub struct Auth {
@@ -229,17 +265,30 @@ fn routing<Io: IoHandle + 'static>(router: Router<Global<Io>>) -> Router<Global<
         .route(GET_POSTS, post(handle::post::bulk_get_info))
         .route(MODIFY_POST, patch(handle::post::modify))
         .route(REVIEW_POST, patch(handle::post::review))
+        .route(BULK_REVIEW_POST, patch(handle::post::bulk_review))
         .route(DELETE_POST, delete(handle::post::remove))
         .route(BULK_DELETE_POST, delete(handle::post::bulk_remove))
         // resource services
         .route(NEW_UPLOAD_SESSION, put(handle::resource::new_session))
+        .route(NEW_TEXT_RESOURCE, put(handle::resource::new_text))
         .route(UPLOAD_RESOURCE, put(handle::resource::upload))
+        .route(UPLOAD_RESOURCE_CHUNK, patch(handle::resource::upload_chunk))
+        .route(GET_UPLOAD_STATUS, get(handle::resource::upload_status))
+        .route(FINISH_UPLOAD, post(handle::resource::finish_upload))
         .route(GET_RESOURCE_PAYLOAD, get(handle::resource::get_payload))
+        .route(GET_RESOURCE_THUMBNAIL, get(handle::resource::get_thumbnail))
+        .route(GET_BUNDLE_FILE, get(handle::resource::get_bundle_file))
         .route(GET_RESOURCE_INFO, get(handle::resource::get_info))
         .route(
             BULK_GET_RESOURCE_INFO,
             post(handle::resource::bulk_get_info),
         )
+        .route(GET_RESOURCE_POSTS, get(handle::resource::get_posts))
+        .route(LIST_MY_RESOURCES, get(handle::resource::list_mine))
+        .route(DELETE_RESOURCE, delete(handle::resource::remove))
+        .route(GET_RESOURCE_GARBAGE, get(handle::resource::get_garbage))
+        .route(SCRUB_RESOURCES, post(handle::resource::scrub))
+        .route(GET_RESOURCE_USAGE, get(handle::resource::get_usage))
         // notification services
         .route(NOTIFY, put(handle::notification::notify))
         .route(FILTER_NOTIFICATIONS, get(handle::notification::filter))
@@ -254,6 +303,8 @@ fn routing<Io: IoHandle + 'static>(router: Router<Global<Io>>) -> Router<Global<
             delete(handle::notification::bulk_remove),
         )
         .route(MODIFY_NOTIFICATION, patch(handle::notification::modify))
+        // statistics services
+        .route(GET_STATS, get(handle::stats::get))
 }
 
 #[cfg(test)]
@@ -43,21 +43,29 @@ pub struct Post {
     grouped: bool,
     /// Priority of this post.
     priority: Priority,
+
+    /// Categories this post is labeled with.
+    categories: Vec<String>,
+
+    /// Resources of this post whose payload files were found
+    /// missing or corrupted by storage scrubbing.
+    damaged_resources: Vec<Id>,
 }
 
-/// Validates the time range of a post.
-pub fn validate_time(time: &RangeInclusive<Date>) -> Result<(), Error> {
+/// Validates the time range of a post with given maximum duration.
+pub fn validate_time(time: &RangeInclusive<Date>, max: Duration) -> Result<(), Error> {
+    let out_of_bound = |given| Error::PostTimeRangeOutOfBound { given, max };
     let dur = Duration::days(
         u32::try_from(
             time.end()
                 .to_julian_day()
                 .checked_sub(time.start().to_julian_day())
-                .ok_or_else(|| Error::PostTimeRangeOutOfBound(Duration::MAX))?,
+                .ok_or_else(|| out_of_bound(Duration::MAX))?,
         )
-        .map_err(|_| Error::PostTimeRangeOutOfBound(Duration::MAX))? as i64,
+        .map_err(|_| out_of_bound(Duration::MAX))? as i64,
     );
-    if dur > Post::MAX_DUR {
-        Err(Error::PostTimeRangeOutOfBound(dur))
+    if dur > max {
+        Err(out_of_bound(dur))
     } else if *time.end() < OffsetDateTime::now_utc().date() {
         Err(Error::PostTimeEnded)
     } else {
@@ -66,10 +74,15 @@ pub fn validate_time(time: &RangeInclusive<Date>) -> Result<(), Error> {
 }
 
 impl Post {
-    /// Maximum duration of a post.
+    /// Default maximum duration of a post.
+    ///
+    /// See [`crate::config::PostMaxDur`] for the configured one.
     pub const MAX_DUR: Duration = Duration::WEEK;
 
     /// Creates a new post.
+    ///
+    /// The time range is validated with given maximum duration.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         title: String,
         notes: String,
@@ -78,8 +91,9 @@ impl Post {
         account: u64,
         grouped: bool,
         priority: Priority,
+        max_dur: Duration,
     ) -> Result<Self, Error> {
-        validate_time(&time)?;
+        validate_time(&time, max_dur)?;
 
         let mut hasher = siphasher::sip::SipHasher24::new();
         title.hash(&mut hasher);
@@ -95,6 +109,8 @@ impl Post {
             states: vec![State::new(Status::Pending, account, notes)],
             grouped,
             priority,
+            categories: vec![],
+            damaged_resources: vec![],
         })
     }
 
@@ -163,10 +179,11 @@ impl Post {
         &self.time
     }
 
-    /// Sets the time range of this post.
+    /// Sets the time range of this post,
+    /// validated with given maximum duration.
     #[inline]
-    pub fn set_time(&mut self, time: RangeInclusive<Date>) -> Result<(), Error> {
-        validate_time(&time)?;
+    pub fn set_time(&mut self, time: RangeInclusive<Date>, max_dur: Duration) -> Result<(), Error> {
+        validate_time(&time, max_dur)?;
         self.time = time;
         Ok(())
     }
@@ -178,11 +195,42 @@ impl Post {
     }
 
     /// Sets the resources used by this post.
+    ///
+    /// Damaged resources no longer used are unmarked.
     #[inline]
     pub fn set_resources(&mut self, resources: Box<[Id]>) {
+        self.damaged_resources.retain(|id| resources.contains(id));
         self.resources = resources
     }
 
+    /// Gets the resources of this post whose payload files were
+    /// found missing or corrupted by storage scrubbing.
+    #[inline]
+    pub fn damaged_resources(&self) -> &[Id] {
+        &self.damaged_resources
+    }
+
+    /// Marks a resource used by this post as damaged.
+    ///
+    /// Returns `false` if the resource is not used by this post,
+    /// or has already been marked.
+    pub fn mark_damaged(&mut self, resource: Id) -> bool {
+        if !self.resources.contains(&resource) || self.damaged_resources.contains(&resource) {
+            return false;
+        }
+        self.damaged_resources.push(resource);
+        true
+    }
+
+    /// Unmarks a damaged resource used by this post.
+    ///
+    /// Returns `false` if the resource has not been marked.
+    pub fn unmark_damaged(&mut self, resource: Id) -> bool {
+        let len = self.damaged_resources.len();
+        self.damaged_resources.retain(|id| *id != resource);
+        self.damaged_resources.len() != len
+    }
+
     /// Whether this post is grouped.
     #[inline]
     pub fn is_grouped(&self) -> bool {
@@ -194,11 +242,26 @@ impl Post {
     pub fn set_is_grouped(&mut self, grouped: bool) {
         self.grouped = grouped
     }
+
+    /// Gets the categories of this post.
+    #[inline]
+    pub fn categories(&self) -> &[String] {
+        &self.categories
+    }
+
+    /// Sets the categories of this post.
+    ///
+    /// Duplicated categories will be removed.
+    pub fn set_categories(&mut self, mut categories: Vec<String>) {
+        categories.sort_unstable();
+        categories.dedup();
+        self.categories = categories
+    }
 }
 
 impl dmds::Data for Post {
     const DIMS: usize = 4;
-    const VERSION: u32 = 1;
+    const VERSION: u32 = 3;
 
     #[inline]
     fn dim(&self, dim: usize) -> u64 {
@@ -215,15 +278,15 @@ impl dmds::Data for Post {
     }
 
     fn decode<B: bytes::Buf>(version: u32, dims: &[u64], buf: B) -> std::io::Result<Self> {
-        match version {
-            1 => bincode::deserialize_from(buf.reader())
-                .map(|mut p: Self| {
-                    p.id = dims[0];
-                    p
-                })
-                .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err)),
+        let mut this: Self = match version {
+            1 => bincode::deserialize_from::<_, legacy::PostV1>(buf.reader()).map(From::from),
+            2 => bincode::deserialize_from::<_, legacy::PostV2>(buf.reader()).map(From::from),
+            3 => bincode::deserialize_from(buf.reader()),
             _ => unreachable!("unsupported data version {version}"),
         }
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))?;
+        this.id = dims[0];
+        Ok(this)
     }
 
     #[inline]
@@ -249,6 +312,10 @@ pub struct State {
 }
 
 impl State {
+    /// Operator of states created by the system itself,
+    /// rather than by an account.
+    pub const SYSTEM_OPERATOR: u64 = 0;
+
     /// Creates a new state.
     #[inline]
     pub fn new(status: Status, account: u64, message: String) -> Self {
@@ -260,6 +327,12 @@ impl State {
         }
     }
 
+    /// Creates a new state operated by the system.
+    #[inline]
+    pub fn system(status: Status, message: String) -> Self {
+        Self::new(status, Self::SYSTEM_OPERATOR, message)
+    }
+
     /// [`Status`] of this state.
     #[inline]
     pub fn status(&self) -> Status {
@@ -286,7 +359,7 @@ impl State {
 }
 
 /// Status of a [`Post`].
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize)]
 pub enum Status {
     /// Pending for review.
     Pending,
@@ -297,7 +370,9 @@ pub enum Status {
 }
 
 /// Deploy priority of a post.
-#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default,
+)]
 #[repr(u8)]
 pub enum Priority {
     /// Blocks all other non-blocking posts while
@@ -312,3 +387,86 @@ pub enum Priority {
     /// Low priority.
     Low = 1,
 }
+
+/// Legacy data representations of [`Post`].
+mod legacy {
+    use std::ops::RangeInclusive;
+
+    use serde::Deserialize;
+    use time::Date;
+
+    use super::{Post, Priority, State};
+    use crate::Id;
+
+    /// [`Post`] of data version 1.
+    #[derive(Deserialize)]
+    pub(super) struct PostV1 {
+        /// Post title.
+        title: String,
+        /// On-screen time range.
+        time: RangeInclusive<Date>,
+        /// List of resource ids this post used.
+        resources: Box<[Id]>,
+        /// Post states in time order.
+        states: Vec<State>,
+        /// Whether this post should be played as
+        /// a full sequence.
+        grouped: bool,
+        /// Priority of this post.
+        priority: Priority,
+    }
+
+    impl From<PostV1> for Post {
+        #[inline]
+        fn from(value: PostV1) -> Self {
+            Self {
+                id: 0,
+                title: value.title,
+                time: value.time,
+                resources: value.resources,
+                states: value.states,
+                grouped: value.grouped,
+                priority: value.priority,
+                categories: vec![],
+                damaged_resources: vec![],
+            }
+        }
+    }
+
+    /// [`Post`] of data version 2.
+    #[derive(Deserialize)]
+    pub(super) struct PostV2 {
+        /// Post title.
+        title: String,
+        /// On-screen time range.
+        time: RangeInclusive<Date>,
+        /// List of resource ids this post used.
+        resources: Box<[Id]>,
+        /// Post states in time order.
+        states: Vec<State>,
+        /// Whether this post should be played as
+        /// a full sequence.
+        grouped: bool,
+        /// Priority of this post.
+        priority: Priority,
+        /// Categories this post is labeled with.
+        categories: Vec<String>,
+    }
+
+    impl From<PostV2> for Post {
+        #[inline]
+        fn from(value: PostV2) -> Self {
+            Self {
+                id: 0,
+                title: value.title,
+                time: value.time,
+                resources: value.resources,
+                states: value.states,
+                grouped: value.grouped,
+                priority: value.priority,
+                categories: value.categories,
+                damaged_resources: vec![],
+            }
+        }
+    }
+}
@@ -11,27 +11,63 @@ use std::time::Instant;
 
 use crate::{Error, Id};
 
+pub mod bundle;
+pub mod meta;
+pub mod pdf;
+pub mod sniff;
+pub mod store;
+pub mod thumbnail;
+pub mod usage;
+pub mod video;
+
+use meta::Metadata;
+
 /// Reference and metadata of a resource file.
 ///
 /// # dmds Dimensions
 ///
 /// ```txt
 /// 0 -> id
-/// 1 -> used (false -> 0, true -> 1)
+/// 1 -> reference count (saturated at 255)
+/// 2 -> content hash
+/// 3 -> owner
 /// ```
+///
+/// Dimensions 2 and 3 were added along with content hashes and owner
+/// listing, and dimension 1 was a usage flag ranged `..2` before
+/// resources could be shared by posts. As items are located by their
+/// dimension values, resource worlds stored with the older layout
+/// are not readable by this one and have to be migrated.
+///
+/// Resources with the same content share the same file,
+/// named by the content hash.
+///
+/// A resource could be used by multiple posts, and is referenced
+/// once by each of them.
+///
+/// Resources without payload files, like [`Variant::Text`],
+/// have a zero content hash.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Resource {
     /// Id of this resource.
     #[serde(skip)]
     id: u64,
     /// Variant of this resource.
+    #[serde(with = "variant_repr")]
     variant: Variant,
     /// Owner of this resource.
     owner: Id,
+    /// Hash of the payload, with [`highway::PortableHash`].
+    hash: u64,
 
-    /// Whether this resource is currently used.
-    #[serde(skip)]
-    used: bool,
+    /// Ids of posts using this resource.
+    posts: Vec<u64>,
+
+    /// Metadata of the payload.
+    metadata: Metadata,
+
+    /// Usage owners charged for the payload.
+    charged: Vec<usage::UsageOwner>,
 }
 
 impl Resource {
@@ -49,7 +85,10 @@ impl Resource {
             id: hasher.finish(),
             variant,
             owner: account,
-            used: false,
+            hash: 0,
+            posts: vec![],
+            metadata: Metadata::default(),
+            charged: vec![],
         }
     }
 
@@ -59,6 +98,12 @@ impl Resource {
         self.id
     }
 
+    /// Hash of the payload of this resource.
+    #[inline]
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
     /// Owner of this resource.
     #[inline]
     pub fn owner(&self) -> Id {
@@ -71,35 +116,111 @@ impl Resource {
         &self.variant
     }
 
-    /// Marks this resource as used.
+    /// Variant of this resource, mutably.
+    #[inline]
+    pub fn variant_mut(&mut self) -> &mut Variant {
+        &mut self.variant
+    }
+
+    /// Metadata of the payload of this resource.
+    #[inline]
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Sets the metadata of the payload of this resource.
+    #[inline]
+    pub fn set_metadata(&mut self, metadata: Metadata) {
+        self.metadata = metadata
+    }
+
+    /// Usage owners charged for the payload of this resource.
     #[inline]
-    pub fn block(&mut self) -> Result<(), Error> {
-        if self.used {
-            return Err(Error::ResourceUsed(self.id));
+    pub fn charged(&self) -> &[usage::UsageOwner] {
+        &self.charged
+    }
+
+    /// Sets the usage owners charged for the payload of this resource.
+    #[inline]
+    pub fn set_charged(&mut self, owners: Vec<usage::UsageOwner>) {
+        self.charged = owners
+    }
+
+    /// Marks this resource as used by the given post,
+    /// increasing the reference count.
+    #[inline]
+    pub fn block(&mut self, post: u64) {
+        if !self.posts.contains(&post) {
+            self.posts.push(post);
         }
-        self.used = true;
-        Ok(())
     }
 
-    /// Marks this resource as unused.
+    /// Marks this resource as unused by the given post,
+    /// decreasing the reference count.
     #[inline]
-    pub fn unblock(&mut self) {
-        self.used = false
+    pub fn unblock(&mut self, post: u64) {
+        self.posts.retain(|p| *p != post)
     }
 
     /// Whether this resource is currently
     /// used by some data.
     #[inline]
     pub fn is_blocked(&self) -> bool {
-        self.used
+        !self.posts.is_empty()
+    }
+
+    /// Ids of posts using this resource.
+    #[inline]
+    pub fn posts(&self) -> &[u64] {
+        &self.posts
+    }
+
+    /// Number of posts using this resource.
+    #[inline]
+    pub fn ref_count(&self) -> usize {
+        self.posts.len()
     }
 
     /// File prefix of a resource.
     const FILE_PREFIX: &'static str = "r_";
 
     /// File name of this resource.
+    #[inline]
     pub fn file_name(&self) -> String {
-        format!("{}{}", Self::FILE_PREFIX, self.id)
+        Self::file_name_of(self.hash)
+    }
+
+    /// File name of resources with given content hash.
+    pub fn file_name_of(hash: u64) -> String {
+        format!("{}{hash}", Self::FILE_PREFIX)
+    }
+
+    /// Thumbnail prefix of a resource.
+    const THUMBNAIL_PREFIX: &'static str = "t";
+
+    /// File name of the thumbnail of this resource with given size.
+    ///
+    /// See [`thumbnail::SIZES`] for available sizes.
+    #[inline]
+    pub fn thumbnail_name(&self, size: u32) -> String {
+        Self::thumbnail_name_of(self.hash, size)
+    }
+
+    /// File name of the thumbnail of resources with given
+    /// content hash and size.
+    pub fn thumbnail_name_of(hash: u64, size: u32) -> String {
+        format!("{}{size}_{hash}", Self::THUMBNAIL_PREFIX)
+    }
+
+    /// Prefix of files of HTML bundles.
+    const BUNDLE_PREFIX: &'static str = "h";
+
+    /// File name of a file in the unpacked HTML bundle of resources
+    /// with given content hash.
+    ///
+    /// The path should be normalized by [`bundle::validate`].
+    pub fn bundle_file_name_of(hash: u64, path: &str) -> String {
+        format!("{}{hash}/{path}", Self::BUNDLE_PREFIX)
     }
 
     /// Buffer prefix of a resource.
@@ -109,32 +230,89 @@ impl Resource {
     pub fn buf_name(&self) -> String {
         format!("{}{}", Self::BUF_PREFIX, self.id)
     }
+
+    /// Accepts the payload hashed by the given hasher, which
+    /// determines the content hash of this resource.
+    ///
+    /// **Id of the resource will be changed**, so you have to
+    /// tell the new id to the frontend.
+    pub fn accept<H: Hasher>(&mut self, mut hasher: H, user: Id) {
+        self.hash = hasher.finish();
+        SystemTime::now().hash(&mut hasher);
+        user.hash(&mut hasher);
+        self.id = hasher.finish();
+    }
+
+    /// Parses the name of a file under the resource directory.
+    ///
+    /// Returns `None` if the file is not managed by resources.
+    pub fn parse_file_name(name: &str) -> Option<FileName> {
+        if let Some(hash) = name.strip_prefix(Self::FILE_PREFIX) {
+            hash.parse().ok().map(FileName::Payload)
+        } else if let Some(id) = name.strip_prefix(Self::BUF_PREFIX) {
+            id.parse().ok().map(FileName::Buf)
+        } else if let Some(rest) = name.strip_prefix(Self::BUNDLE_PREFIX) {
+            let (hash, _) = rest.split_once('/')?;
+            hash.parse().ok().map(FileName::Bundle)
+        } else {
+            let (size, hash) = name.strip_prefix(Self::THUMBNAIL_PREFIX)?.split_once('_')?;
+            Some(FileName::Thumbnail {
+                size: size.parse().ok()?,
+                hash: hash.parse().ok()?,
+            })
+        }
+    }
+}
+
+/// Name of a file under the resource directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileName {
+    /// Payload file of resources with the content hash.
+    Payload(u64),
+    /// Thumbnail of resources with the content hash.
+    Thumbnail {
+        /// Size of the thumbnail.
+        size: u32,
+        /// Content hash of the resources.
+        hash: u64,
+    },
+    /// File in the unpacked HTML bundle of resources with the content hash.
+    Bundle(u64),
+    /// Upload buffer of the session with the id.
+    Buf(u64),
 }
 
 impl dmds::Data for Resource {
-    const DIMS: usize = 2;
-    const VERSION: u32 = 1;
+    const DIMS: usize = 4;
+    const VERSION: u32 = 2;
 
     #[inline]
     fn dim(&self, dim: usize) -> u64 {
         match dim {
             0 => self.id,
-            1 => self.used as u64,
+            1 => self.ref_count().min(u8::MAX as usize) as u64,
+            2 => self.hash,
+            3 => self.owner.0,
             _ => unreachable!(),
         }
     }
 
     fn decode<B: bytes::Buf>(version: u32, dims: &[u64], buf: B) -> std::io::Result<Self> {
-        match version {
-            0 => {
-                let mut this: Self = bincode::deserialize_from(buf.reader())
-                    .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))?;
-                this.id = dims[0];
-                this.used = dims[1] != 0;
-                Ok(this)
+        let mut this: Self = match version {
+            // Variants were encoded as internally tagged enums,
+            // which could never be deserialized by bincode.
+            0 | 1 => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("undecodable resource data version {version}"),
+                ))
             }
-            _ => unreachable!(),
+            2 => bincode::deserialize_from(buf.reader()),
+            _ => unreachable!("unsupported data version {version}"),
         }
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))?;
+        this.id = dims[0];
+        Ok(this)
     }
 
     #[inline]
@@ -144,13 +322,43 @@ impl dmds::Data for Resource {
     }
 }
 
+/// Serializes [`Variant`] as a JSON string, as internally tagged
+/// enums are not supported by non-self-describing formats like bincode.
+mod variant_repr {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::Variant;
+
+    /// Serializes the variant.
+    pub(super) fn serialize<S: Serializer>(
+        variant: &Variant,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serde_json::to_string(variant)
+            .map_err(serde::ser::Error::custom)?
+            .serialize(serializer)
+    }
+
+    /// Deserializes the variant.
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Variant, D::Error> {
+        serde_json::from_str(&String::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+    }
+}
+
 /// A resource uploading session.
 #[derive(Debug)]
 struct UploadSession {
     /// Resource being uploaded.
     resource: Resource,
-    /// Time of creation.
+    /// Time of the last activity.
     instant: Instant,
+
+    /// Number of bytes received.
+    received: u64,
+    /// Whether the buffer is being written.
+    writing: bool,
 }
 
 impl UploadSession {
@@ -160,16 +368,26 @@ impl UploadSession {
         Self {
             resource,
             instant: Instant::now(),
+            received: 0,
+            writing: false,
         }
     }
 
-    /// Expire duration of a session.
-    const EXPIRE_DUR: time::Duration = time::Duration::seconds(15);
+    /// Expire duration of a session since its last activity.
+    const EXPIRE_DUR: time::Duration = time::Duration::minutes(10);
 
     /// Whether this session is expired.
+    ///
+    /// A session being written is never expired.
     #[inline]
     fn is_expired(&self) -> bool {
-        self.instant.elapsed() > Self::EXPIRE_DUR
+        !self.writing && self.instant.elapsed() > Self::EXPIRE_DUR
+    }
+
+    /// Marks this session as active.
+    #[inline]
+    fn touch(&mut self) {
+        self.instant = Instant::now()
     }
 }
 
@@ -213,38 +431,94 @@ impl UploadSessions {
         self.inner.insert(res.id, res.into());
     }
 
+    /// Gets a session owned by the given user mutably.
+    fn get_owned_mut(&mut self, id: u64, user: Id) -> Result<&mut UploadSession, Error> {
+        self.cleanup();
+        let session = self
+            .inner
+            .get_mut(&id)
+            .ok_or(Error::ResourceUploadSessionNotFound(id))?;
+        if session.resource.owner != user {
+            return Err(Error::PermissionDenied);
+        }
+        Ok(session)
+    }
+
+    /// Number of bytes received by a session.
+    pub fn received(&mut self, id: u64, user: Id) -> Result<u64, Error> {
+        self.get_owned_mut(id, user).map(|s| s.received)
+    }
+
+    /// Begins writing the buffer of a session from given offset,
+    /// and returns the buffer name.
+    ///
+    /// The offset should not be greater than the number of bytes
+    /// received, so the client could resend data it's unsure about.
+    /// [`Self::end_write`] must be called after writing.
+    pub fn begin_write(&mut self, id: u64, user: Id, offset: u64) -> Result<String, Error> {
+        let session = self.get_owned_mut(id, user)?;
+        if session.writing {
+            return Err(Error::ResourceUploadBusy(id));
+        }
+        if offset > session.received {
+            return Err(Error::ResourceUploadOffsetMismatch {
+                expected: session.received,
+            });
+        }
+        session.writing = true;
+        session.received = offset;
+        session.touch();
+        Ok(session.resource.buf_name())
+    }
+
+    /// Ends writing the buffer of a session, with number of bytes
+    /// written from the offset if succeeded.
+    pub fn end_write(&mut self, id: u64, written: Option<u64>) {
+        if let Some(session) = self.inner.get_mut(&id) {
+            session.writing = false;
+            session.received += written.unwrap_or_default();
+            session.touch();
+        }
+    }
+
     /// Accepts the body of a resource with given id,
     /// and returns the resource.
     ///
+    /// The given hasher should have hashed the whole payload,
+    /// which determines the content hash of the resource.
+    ///
     /// **Id of the resource will be changed**, so you have to
     /// tell the new id to the frontend.
-    pub fn accept<H: Hasher>(
-        &mut self,
-        id: Id,
-        mut hasher: H,
-        user: Id,
-    ) -> Result<Resource, Error> {
-        self.cleanup();
-        let res = &self
-            .inner
-            .get(&id.0)
-            .ok_or(Error::ResourceUploadSessionNotFound(id.0))?
-            .resource;
-        if res.owner != user {
-            return Err(Error::PermissionDenied);
+    pub fn accept<H: Hasher>(&mut self, id: Id, hasher: H, user: Id) -> Result<Resource, Error> {
+        let mut res = self.take(id.0, user)?;
+        res.accept(hasher, user);
+        Ok(res)
+    }
+
+    /// Takes a session owned by the given user out of the storage,
+    /// so it could be finalized exclusively, and returns the resource.
+    ///
+    /// The resource could be put back by [`Self::insert`],
+    /// with received data discarded.
+    pub fn take(&mut self, id: u64, user: Id) -> Result<Resource, Error> {
+        if self.get_owned_mut(id, user)?.writing {
+            return Err(Error::ResourceUploadBusy(id));
         }
+        Ok(self.inner.remove(&id).unwrap().resource)
+    }
 
-        let mut res = self.inner.remove(&id.0).unwrap().resource;
-        SystemTime::now().hash(&mut hasher);
-        user.hash(&mut hasher);
-        res.id = hasher.finish();
-        Ok(res)
+    /// Whether the session with given id is still alive.
+    #[inline]
+    pub fn contains(&mut self, id: u64) -> bool {
+        self.cleanup();
+        self.inner.contains_key(&id)
     }
 
-    /// Gets filesystem buffer name of a resource session.
+    /// Gets the declared variant of a resource session
+    /// owned by the given user.
     #[inline]
-    pub fn buf_name(&self, id: u64) -> Option<String> {
-        self.inner.get(&id).map(|s| s.resource.buf_name())
+    pub fn variant(&mut self, id: u64, user: Id) -> Result<&Variant, Error> {
+        self.get_owned_mut(id, user).map(|s| &s.resource.variant)
     }
 }
 
@@ -260,6 +534,10 @@ pub enum Variant {
         duration: u32,
     },
     /// A PDF file.
+    ///
+    /// If `pages` is `0` and `durations` is empty, they will be
+    /// filled in by the server after the file is uploaded,
+    /// with [`Variant::DEFAULT_PAGE_DURATION`] for each page.
     Pdf {
         /// Number of pages.
         pages: u16,
@@ -269,8 +547,236 @@ pub enum Variant {
         durations: Box<[u32]>,
     },
     /// A video file.
+    ///
+    /// If `duration` is `0`, it will be filled in by the server
+    /// after the file is uploaded, if the container declares it.
     Video {
         /// Video duration, as seconds.
         duration: u32,
     },
+    /// An HTML bundle, uploaded as a zip archive and unpacked.
+    ///
+    /// `files` will be filled in by the server after the
+    /// bundle is uploaded.
+    Html {
+        /// Path of the entry HTML file in the bundle.
+        entry: String,
+        /// Duration this page is displayed, as seconds.
+        duration: u32,
+        /// Paths of files in the bundle.
+        #[serde(default)]
+        files: Box<[String]>,
+    },
+    /// A text slide, stored inline without a payload file.
+    ///
+    /// Screens render it natively.
+    Text {
+        /// Title of the slide.
+        title: String,
+        /// Body of the slide, in Markdown.
+        body: String,
+        /// Display styling hints.
+        #[serde(default)]
+        style: TextStyle,
+        /// Duration this slide is displayed, as seconds.
+        duration: u32,
+    },
+}
+
+/// Display styling hints of a [`Variant::Text`].
+///
+/// Screens may ignore hints they don't support.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+pub struct TextStyle {
+    /// Horizontal alignment of the text.
+    #[serde(default)]
+    pub align: TextAlign,
+    /// Font size of the body, as pixels.
+    #[serde(default)]
+    pub font_size: Option<u16>,
+    /// Text color, as a CSS color.
+    #[serde(default)]
+    pub color: Option<String>,
+    /// Background color, as a CSS color.
+    #[serde(default)]
+    pub background: Option<String>,
+}
+
+impl TextStyle {
+    /// Range of font sizes of the body, as pixels.
+    pub const FONT_SIZE_RANGE: std::ops::RangeInclusive<u16> = 8..=256;
+    /// Maximum length of a color, as bytes.
+    pub const MAX_COLOR_LEN: usize = 32;
+
+    /// Validates this style declared by the client.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self
+            .font_size
+            .is_some_and(|size| !Self::FONT_SIZE_RANGE.contains(&size))
+        {
+            return Err(Error::InvalidResourceVariant("font size out of range"));
+        }
+        if self.color.as_deref().is_some_and(|c| !Self::is_color(c)) {
+            return Err(Error::InvalidResourceVariant("invalid text color"));
+        }
+        if self
+            .background
+            .as_deref()
+            .is_some_and(|c| !Self::is_color(c))
+        {
+            return Err(Error::InvalidResourceVariant("invalid background color"));
+        }
+        Ok(())
+    }
+
+    /// Whether the value is a hex color like `#fff` and `#ffffff80`,
+    /// or a named color like `crimson`.
+    fn is_color(value: &str) -> bool {
+        if value.len() > Self::MAX_COLOR_LEN {
+            return false;
+        }
+        match value.strip_prefix('#') {
+            Some(hex) => {
+                matches!(hex.len(), 3 | 4 | 6 | 8) && hex.bytes().all(|b| b.is_ascii_hexdigit())
+            }
+            None => !value.is_empty() && value.bytes().all(|b| b.is_ascii_alphabetic()),
+        }
+    }
+}
+
+/// Horizontal alignment of text.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextAlign {
+    /// Aligned to the left.
+    #[default]
+    Left,
+    /// Centered.
+    Center,
+    /// Aligned to the right.
+    Right,
+}
+
+impl Variant {
+    /// Default duration of a PDF page, as seconds.
+    pub const DEFAULT_PAGE_DURATION: u32 = 10;
+
+    /// Maximum difference between the declared and actual
+    /// duration of a video, as seconds.
+    pub const VIDEO_DURATION_TOLERANCE: u32 = 2;
+
+    /// Maximum length of the title of a text slide, as bytes.
+    pub const MAX_TEXT_TITLE_LEN: usize = 256;
+    /// Maximum length of the body of a text slide, as bytes.
+    pub const MAX_TEXT_BODY_LEN: usize = 8 * 1024;
+
+    /// Validates this variant declared by the client.
+    pub fn validate(&self) -> Result<(), Error> {
+        match self {
+            Variant::Pdf { pages, durations } if durations.len() != *pages as usize => Err(
+                Error::InvalidResourceVariant("number of page durations mismatches pages"),
+            ),
+            Variant::Text { title, .. } if title.len() > Self::MAX_TEXT_TITLE_LEN => {
+                Err(Error::InvalidResourceVariant("text title too long"))
+            }
+            Variant::Text { body, .. } if body.len() > Self::MAX_TEXT_BODY_LEN => {
+                Err(Error::InvalidResourceVariant("text body too long"))
+            }
+            Variant::Html { entry, .. } if !bundle::is_entry(entry) => Err(
+                Error::InvalidResourceVariant("entry of an HTML bundle should be an HTML file"),
+            ),
+            Variant::Text { title, body, .. }
+                if title.trim().is_empty() && body.trim().is_empty() =>
+            {
+                Err(Error::InvalidResourceVariant("text slide is empty"))
+            }
+            Variant::Text { style, .. } => style.validate(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether resources of this variant have payload files.
+    ///
+    /// Variants without payload files are stored inline,
+    /// and could not be uploaded.
+    #[inline]
+    pub fn has_payload(&self) -> bool {
+        !matches!(self, Variant::Text { .. })
+    }
+
+    /// Checks the declared pages of a PDF variant with the
+    /// actual page count, or fills them in if not declared.
+    ///
+    /// This does nothing for other variants.
+    pub fn fit_pdf_pages(&mut self, actual: u32) -> Result<(), Error> {
+        if let Variant::Pdf { pages, durations } = self {
+            let actual = u16::try_from(actual).map_err(|_| Error::PdfPagesMismatch {
+                declared: *pages,
+                actual,
+            })?;
+            if *pages == 0 && durations.is_empty() {
+                *pages = actual;
+                *durations = vec![Self::DEFAULT_PAGE_DURATION; actual as usize].into_boxed_slice();
+            } else if *pages != actual {
+                return Err(Error::PdfPagesMismatch {
+                    declared: *pages,
+                    actual: actual as u32,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks the declared duration of a video variant with the
+    /// actual duration, or fills it in if not declared.
+    ///
+    /// Declared durations within [`Self::VIDEO_DURATION_TOLERANCE`]
+    /// are corrected to the actual duration.
+    ///
+    /// This does nothing for other variants.
+    pub fn fit_video_duration(&mut self, actual: u32) -> Result<(), Error> {
+        if let Variant::Video { duration } = self {
+            if *duration != 0 && duration.abs_diff(actual) > Self::VIDEO_DURATION_TOLERANCE {
+                return Err(Error::VideoDurationMismatch {
+                    declared: *duration,
+                    actual,
+                });
+            }
+            *duration = actual;
+        }
+        Ok(())
+    }
+
+    /// Fills in files of an HTML bundle variant.
+    ///
+    /// This does nothing for other variants.
+    #[inline]
+    pub fn fill_html_files(&mut self, paths: Box<[String]>) {
+        if let Variant::Html { files, .. } = self {
+            *files = paths
+        }
+    }
+
+    /// MIME type of payloads of this variant, used when
+    /// the format of a payload is unknown.
+    #[inline]
+    pub fn mime(&self) -> &'static str {
+        match self {
+            Variant::Pdf { .. } => "application/pdf",
+            Variant::Text { .. } => "text/markdown",
+            Variant::Html { .. } => "application/zip",
+            Variant::Image { .. } | Variant::Video { .. } => "application/octet-stream",
+        }
+    }
+
+    /// Name of this variant's type.
+    #[inline]
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Variant::Image { .. } => "Image",
+            Variant::Pdf { .. } => "Pdf",
+            Variant::Video { .. } => "Video",
+            Variant::Text { .. } => "Text",
+            Variant::Html { .. } => "Html",
+        }
+    }
 }
@@ -0,0 +1,180 @@
+//! HTML bundles, uploaded as zip archives.
+//!
+//! Files of a bundle are unpacked into the resource store under
+//! [`super::Resource::bundle_file_name_of`], and served under a
+//! sandboxed path prefix with [`CSP`].
+
+use std::{
+    io::{Read, Seek},
+    path::Component,
+};
+
+use crate::Error;
+
+/// Maximum number of files in a bundle.
+pub const MAX_FILES: usize = 256;
+
+/// Maximum total length of unpacked files of a bundle, as bytes.
+pub const MAX_UNPACKED_LEN: u64 = 100 * 1024 * 1024;
+
+/// Maximum length of a file path in a bundle, as bytes.
+pub const MAX_PATH_LEN: usize = 256;
+
+/// Content-Security-Policy of served bundle files.
+///
+/// Bundles could only run their own scripts in an opaque origin,
+/// and could not reach the network.
+pub const CSP: &str = "sandbox allow-scripts; default-src 'none'; \
+    script-src 'self' 'unsafe-inline'; style-src 'self' 'unsafe-inline'; \
+    img-src 'self' data:; font-src 'self' data:; media-src 'self'; \
+    connect-src 'none'; form-action 'none'; base-uri 'none'";
+
+/// Normalizes a path of a file in a bundle, with `/` as the separator.
+///
+/// Returns `None` if the path escapes the bundle root.
+fn normalize(path: &std::path::Path) -> Option<String> {
+    let mut components = vec![];
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => components.push(name.to_str()?),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    let path = components.join("/");
+    (!path.is_empty() && path.len() <= MAX_PATH_LEN).then_some(path)
+}
+
+/// Reads the archive, and calls `f` with path, declared length
+/// and a reader of content of each file.
+fn walk<R: Read + Seek>(
+    reader: R,
+    mut f: impl FnMut(String, u64, &mut dyn Read) -> Result<(), Error>,
+) -> Result<(), Error> {
+    let mut archive =
+        zip::ZipArchive::new(reader).map_err(|_| Error::InvalidHtmlBundle("not a zip archive"))?;
+    if archive.len() > MAX_FILES {
+        return Err(Error::InvalidHtmlBundle("too many files"));
+    }
+    let mut total = 0u64;
+    for i in 0..archive.len() {
+        let mut file = archive
+            .by_index(i)
+            .map_err(|_| Error::InvalidHtmlBundle("corrupted zip archive"))?;
+        if file.is_dir() {
+            continue;
+        }
+        let path = file
+            .enclosed_name()
+            .as_deref()
+            .and_then(normalize)
+            .ok_or(Error::InvalidHtmlBundle("invalid file path"))?;
+        let size = file.size();
+        total = total.saturating_add(size);
+        if total > MAX_UNPACKED_LEN {
+            return Err(Error::InvalidHtmlBundle("unpacked files too large"));
+        }
+        f(path, size, &mut file)?;
+    }
+    Ok(())
+}
+
+/// Validates a bundle with given entry file, and returns
+/// paths of files in it and total length of them, as bytes.
+pub fn validate<R: Read + Seek>(reader: R, entry: &str) -> Result<(Box<[String]>, u64), Error> {
+    let mut files = vec![];
+    let mut total = 0;
+    walk(reader, |path, size, _| {
+        files.push(path);
+        total += size;
+        Ok(())
+    })?;
+    if !files.iter().any(|path| path == entry) {
+        return Err(Error::InvalidHtmlBundle("entry file not found"));
+    }
+    Ok((files.into_boxed_slice(), total))
+}
+
+/// Unpacks files of a bundle, and calls `f` with path and
+/// a reader of content of each file, so files could be
+/// streamed without being held in memory.
+///
+/// Contents should be read to the end. Reading errors with
+/// [`std::io::ErrorKind::InvalidData`] if a file is not as long
+/// as declared, as declared lengths can't be trusted.
+///
+/// Unpacking stops at the first error returned by `f`.
+pub fn unpack<R: Read + Seek>(
+    reader: R,
+    mut f: impl FnMut(String, &mut dyn Read) -> Result<(), Error>,
+) -> Result<(), Error> {
+    walk(reader, |path, size, file| {
+        f(
+            path,
+            &mut Content {
+                inner: file.take(size + 1),
+                left: size,
+            },
+        )
+    })
+}
+
+/// Content of a file in a bundle, checked against its declared length.
+struct Content<R> {
+    inner: std::io::Take<R>,
+    /// Bytes left to be read.
+    left: u64,
+}
+
+impl<R: Read> Read for Content<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        if (len == 0 && self.left > 0 && !buf.is_empty()) || len as u64 > self.left {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "file length mismatches the declared length",
+            ));
+        }
+        self.left -= len as u64;
+        Ok(len)
+    }
+}
+
+/// Whether the path could be an entry file of a bundle.
+#[inline]
+pub fn is_entry(path: &str) -> bool {
+    let path = path.to_ascii_lowercase();
+    path.ends_with(".html") || path.ends_with(".htm")
+}
+
+/// MIME type of a file in a bundle, from its extension.
+pub fn mime_of(path: &str) -> &'static str {
+    let ext = path
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_ascii_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "json" => "application/json",
+        "txt" => "text/plain; charset=utf-8",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "mp3" => "audio/mpeg",
+        "ogg" => "audio/ogg",
+        "wav" => "audio/wav",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "wasm" => "application/wasm",
+        _ => "application/octet-stream",
+    }
+}
@@ -0,0 +1,180 @@
+//! Metadata extraction of resource payloads.
+
+use std::io::Cursor;
+
+use image::ImageFormat;
+use serde::{Deserialize, Serialize};
+
+use super::{sniff::Format, video::VideoMeta};
+
+/// Metadata of a resource payload, extracted while uploading.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Metadata {
+    /// Size of the payload, as bytes.
+    pub size: u64,
+    /// Format of the payload.
+    pub format: Option<Format>,
+    /// Metadata of the image.\
+    /// This only presents for images.
+    pub image: Option<ImageMeta>,
+    /// Metadata of the video.\
+    /// This only presents for videos.
+    pub video: Option<VideoMeta>,
+    /// Total length of unpacked files, as bytes.\
+    /// This only presents for HTML bundles.
+    pub unpacked: Option<u64>,
+}
+
+impl Metadata {
+    /// Length charged to storage quotas, as bytes.
+    ///
+    /// Unpacked files of HTML bundles are stored next to
+    /// the payload, so they are charged as well.
+    #[inline]
+    pub fn charged_size(&self) -> u64 {
+        self.size + self.unpacked.unwrap_or(0)
+    }
+}
+
+/// Metadata of an image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImageMeta {
+    /// Width of the image, as pixels.
+    pub width: u32,
+    /// Height of the image, as pixels.
+    pub height: u32,
+    /// EXIF orientation of the image, from `1` to `8`.
+    ///
+    /// `1` means the image should be displayed as is.
+    pub orientation: u8,
+}
+
+impl ImageMeta {
+    /// Maximum length of the leading bytes of an image
+    /// to extract metadata from, as bytes.
+    pub const MAX_HEADER_LEN: u64 = 1024 * 1024;
+
+    /// Extracts metadata of an image with given format.
+    ///
+    /// Only the header of the image is decoded, so `bytes` could be
+    /// the leading [`Self::MAX_HEADER_LEN`] bytes of the image.
+    ///
+    /// Returns `None` if the format is not an image format,
+    /// or the image is malformed.
+    pub fn extract(format: Format, bytes: &[u8]) -> Option<Self> {
+        let image_format = match format {
+            Format::Png => ImageFormat::Png,
+            Format::Jpeg => ImageFormat::Jpeg,
+            Format::WebP => ImageFormat::WebP,
+            Format::Gif => ImageFormat::Gif,
+            _ => return None,
+        };
+        let (width, height) = image::io::Reader::with_format(Cursor::new(bytes), image_format)
+            .into_dimensions()
+            .ok()?;
+        let orientation = if format == Format::Jpeg {
+            jpeg_orientation(bytes).unwrap_or(1)
+        } else {
+            1
+        };
+        Some(Self {
+            width,
+            height,
+            orientation,
+        })
+    }
+}
+
+/// Reads a big-endian `u16` at given offset.
+#[inline]
+pub(super) fn be_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(
+        bytes.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+/// Reads a big-endian `u32` at given offset.
+#[inline]
+pub(super) fn be_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(
+        bytes.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+/// Reads a big-endian `u64` at given offset.
+#[inline]
+pub(super) fn be_u64(bytes: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(
+        bytes.get(offset..offset + 8)?.try_into().ok()?,
+    ))
+}
+
+/// Reads a little-endian `u16` at given offset.
+#[inline]
+fn le_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        bytes.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+/// Reads a little-endian `u32` at given offset.
+#[inline]
+fn le_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        bytes.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+/// Gets the EXIF orientation of a JPEG image, by scanning
+/// its segments before the frame header.
+fn jpeg_orientation(bytes: &[u8]) -> Option<u8> {
+    let mut offset = 2;
+    loop {
+        if *bytes.get(offset)? != 0xff {
+            return None;
+        }
+        let marker = *bytes.get(offset + 1)?;
+        offset += 2;
+        match marker {
+            // Fill bytes.
+            0xff => offset -= 1,
+            // Markers without payload.
+            0x01 | 0xd0..=0xd8 => {}
+            // Start of frame, excluding DHT, JPG and DAC, or start of scan,
+            // where the EXIF segment should have been found.
+            0xc0..=0xcf if !matches!(marker, 0xc4 | 0xc8 | 0xcc) => return None,
+            0xda | 0xd9 => return None,
+            _ => {
+                let len = be_u16(bytes, offset)? as usize;
+                let segment = bytes.get(offset + 2..offset + len)?;
+                if marker == 0xe1 && segment.starts_with(b"Exif\0\0") {
+                    return exif_orientation(&segment[6..]);
+                }
+                offset += len;
+            }
+        }
+    }
+}
+
+/// Gets the orientation from the first IFD of a TIFF structure.
+fn exif_orientation(tiff: &[u8]) -> Option<u8> {
+    /// Tag of orientation.
+    const ORIENTATION: u16 = 0x0112;
+
+    let (u16_at, u32_at): (
+        fn(&[u8], usize) -> Option<u16>,
+        fn(&[u8], usize) -> Option<u32>,
+    ) = match tiff.get(0..2)? {
+        b"II" => (le_u16, le_u32),
+        b"MM" => (be_u16, be_u32),
+        _ => return None,
+    };
+    let ifd = u32_at(tiff, 4)? as usize;
+    let entries = u16_at(tiff, ifd)? as usize;
+    (0..entries)
+        .map(|i| ifd + 2 + i * 12)
+        .find(|&entry| u16_at(tiff, entry) == Some(ORIENTATION))
+        .and_then(|entry| u16_at(tiff, entry + 8))
+        .and_then(|o| u8::try_from(o).ok())
+        .filter(|o| (1..=8).contains(o))
+}
@@ -0,0 +1,36 @@
+//! PDF payload inspection.
+
+/// Maximum length of PDF documents to be parsed, as bytes.
+///
+/// Documents are parsed in memory, so larger ones are rejected.
+pub const MAX_PARSED_LEN: u64 = 64 * 1024 * 1024;
+
+/// Counts pages of a PDF document.
+///
+/// Returns `None` if the document could not be parsed.
+pub fn count_pages(bytes: &[u8]) -> Option<u32> {
+    lopdf::Document::load_mem(bytes)
+        .ok()
+        .map(|doc| doc.get_pages().len() as u32)
+}
+
+/// Gets the largest JPEG image embedded in the first page
+/// of a PDF document, as an approximation of its rendering.
+///
+/// Returns `None` if there is no such image, or the document
+/// could not be parsed.
+pub fn first_page_jpeg(bytes: &[u8]) -> Option<Vec<u8>> {
+    let doc = lopdf::Document::load_mem(bytes).ok()?;
+    let (_, &page) = doc.get_pages().iter().next()?;
+    doc.get_page_images(page)
+        .ok()?
+        .into_iter()
+        .filter(|image| {
+            image
+                .filters
+                .as_ref()
+                .is_some_and(|f| f.iter().any(|f| f == "DCTDecode"))
+        })
+        .max_by_key(|image| image.width * image.height)
+        .map(|image| image.content.to_vec())
+}
@@ -0,0 +1,88 @@
+//! Content sniffing of resource payloads.
+
+use serde::{Deserialize, Serialize};
+
+use super::Variant;
+
+/// Format of a resource payload, detected from its
+/// magic bytes and container headers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Format {
+    /// PNG image.
+    Png,
+    /// JPEG image.
+    Jpeg,
+    /// WebP image.
+    WebP,
+    /// GIF image.
+    Gif,
+    /// PDF document.
+    Pdf,
+    /// MP4 video, or other ISO base media file format videos.
+    Mp4,
+    /// WebM video.
+    WebM,
+    /// Zip archive.
+    Zip,
+}
+
+impl Format {
+    /// Number of leading bytes needed for detecting the format.
+    pub const HEADER_LEN: usize = 64;
+
+    /// ISO base media file format brands of still images,
+    /// which should not be treated as videos.
+    const IMAGE_BRANDS: [&'static [u8; 4]; 6] =
+        [b"heic", b"heix", b"mif1", b"msf1", b"avif", b"avis"];
+
+    /// Detects the format from leading bytes of a payload.
+    ///
+    /// Returns `None` if the format is unknown.
+    pub fn detect(header: &[u8]) -> Option<Self> {
+        match header {
+            [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n', ..] => Some(Self::Png),
+            [0xff, 0xd8, 0xff, ..] => Some(Self::Jpeg),
+            [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some(Self::WebP),
+            [b'G', b'I', b'F', b'8', b'7' | b'9', b'a', ..] => Some(Self::Gif),
+            [b'%', b'P', b'D', b'F', b'-', ..] => Some(Self::Pdf),
+            [b'P', b'K', 3, 4, ..] => Some(Self::Zip),
+            [_, _, _, _, b'f', b't', b'y', b'p', b0, b1, b2, b3, ..]
+                if !Self::IMAGE_BRANDS.contains(&&[*b0, *b1, *b2, *b3]) =>
+            {
+                Some(Self::Mp4)
+            }
+            // EBML header with `webm` doc type, rather than `matroska`.
+            [0x1a, 0x45, 0xdf, 0xa3, rest @ ..] if rest.windows(4).any(|w| w == b"webm") => {
+                Some(Self::WebM)
+            }
+            _ => None,
+        }
+    }
+
+    /// MIME type of this format.
+    pub fn mime(self) -> &'static str {
+        match self {
+            Format::Png => "image/png",
+            Format::Jpeg => "image/jpeg",
+            Format::WebP => "image/webp",
+            Format::Gif => "image/gif",
+            Format::Pdf => "application/pdf",
+            Format::Mp4 => "video/mp4",
+            Format::WebM => "video/webm",
+            Format::Zip => "application/zip",
+        }
+    }
+
+    /// Whether this format is acceptable for the given variant.
+    pub fn matches(self, variant: &Variant) -> bool {
+        matches!(
+            (self, variant),
+            (
+                Format::Png | Format::Jpeg | Format::WebP | Format::Gif,
+                Variant::Image { .. }
+            ) | (Format::Pdf, Variant::Pdf { .. })
+                | (Format::Mp4 | Format::WebM, Variant::Video { .. })
+                | (Format::Zip, Variant::Html { .. })
+        )
+    }
+}
@@ -0,0 +1,400 @@
+//! S3-compatible object storage client.
+//!
+//! Requests are signed with AWS Signature Version 4 and use path-style
+//! addressing, so the client works with most self-hosted implementations.
+
+use std::{io, ops::RangeInclusive, path::Path, time::SystemTime};
+
+use bytes::Bytes;
+use futures::TryStreamExt;
+use hmac::{Hmac, Mac};
+use reqwest::{header, Method, StatusCode};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use time::OffsetDateTime;
+
+use super::{BoxFuture, ByteStream, FileMeta, ResourceStore};
+
+/// Configuration of an S3-compatible object storage.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Config {
+    /// Endpoint of the service, like `https://s3.example.com`.
+    pub endpoint: String,
+    /// Name of the bucket.
+    pub bucket: String,
+    /// Region of the bucket.
+    #[serde(default = "Config::default_region")]
+    pub region: String,
+    /// Access key id.
+    pub access_key: String,
+    /// Secret access key.
+    pub secret_key: String,
+    /// Prefix of object keys, like `resources/`.
+    #[serde(default)]
+    pub prefix: String,
+}
+
+impl Config {
+    /// Default region of buckets.
+    #[inline]
+    fn default_region() -> String {
+        "us-east-1".to_owned()
+    }
+}
+
+/// Hash of an unsigned payload.
+const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+
+/// Signing algorithm.
+const ALGORITHM: &str = "AWS4-HMAC-SHA256";
+
+/// An S3-compatible object storage client.
+#[derive(Debug)]
+pub struct S3 {
+    /// Configuration of the storage.
+    config: Config,
+    /// The HTTP client.
+    client: reqwest::Client,
+}
+
+impl S3 {
+    /// Creates a client with given configuration.
+    pub fn new(mut config: Config) -> Self {
+        while config.endpoint.ends_with('/') {
+            config.endpoint.pop();
+        }
+        Self {
+            config,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Host of the endpoint, including the port if any.
+    fn host(&self) -> &str {
+        let endpoint = &self.config.endpoint;
+        let rest = endpoint
+            .split_once("://")
+            .map_or(endpoint.as_str(), |(_, rest)| rest);
+        rest.split('/').next().unwrap_or(rest)
+    }
+
+    /// Canonical URI of an object, or of the bucket if the name is empty.
+    fn uri(&self, name: &str) -> String {
+        let mut uri = format!("/{}", uri_encode(&self.config.bucket, true));
+        if !name.is_empty() {
+            uri.push('/');
+            uri.push_str(&uri_encode(&format!("{}{name}", self.config.prefix), false));
+        }
+        uri
+    }
+
+    /// Builds a signed request.
+    fn request(
+        &self,
+        method: Method,
+        name: &str,
+        query: &[(&str, &str)],
+        headers: &[(&str, String)],
+    ) -> reqwest::RequestBuilder {
+        let now = OffsetDateTime::now_utc();
+        let date = format!("{:04}{:02}{:02}", now.year(), now.month() as u8, now.day());
+        let datetime = format!(
+            "{date}T{:02}{:02}{:02}Z",
+            now.hour(),
+            now.minute(),
+            now.second()
+        );
+
+        let uri = self.uri(name);
+        let mut query: Vec<_> = query
+            .iter()
+            .map(|(k, v)| (uri_encode(k, true), uri_encode(v, true)))
+            .collect();
+        query.sort();
+        let query = query
+            .iter()
+            .map(|(k, v)| format!("{k}={v}"))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        let mut signed_headers: Vec<(String, String)> = vec![
+            ("host".to_owned(), self.host().to_owned()),
+            (
+                "x-amz-content-sha256".to_owned(),
+                UNSIGNED_PAYLOAD.to_owned(),
+            ),
+            ("x-amz-date".to_owned(), datetime.clone()),
+        ];
+        signed_headers.extend(
+            headers
+                .iter()
+                .map(|(k, v)| (k.to_ascii_lowercase(), v.trim().to_owned())),
+        );
+        signed_headers.sort();
+        let canonical_headers: String = signed_headers
+            .iter()
+            .map(|(k, v)| format!("{k}:{v}\n"))
+            .collect();
+        let signed_header_names = signed_headers
+            .iter()
+            .map(|(k, _)| k.as_str())
+            .collect::<Vec<_>>()
+            .join(";");
+
+        let canonical_request = format!(
+            "{method}\n{uri}\n{query}\n{canonical_headers}\n{signed_header_names}\n{UNSIGNED_PAYLOAD}"
+        );
+        let scope = format!("{date}/{}/s3/aws4_request", self.config.region);
+        let string_to_sign = format!(
+            "{ALGORITHM}\n{datetime}\n{scope}\n{}",
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+
+        let key = hmac(
+            format!("AWS4{}", self.config.secret_key).as_bytes(),
+            date.as_bytes(),
+        );
+        let key = hmac(&key, self.config.region.as_bytes());
+        let key = hmac(&key, b"s3");
+        let key = hmac(&key, b"aws4_request");
+        let signature = hex::encode(hmac(&key, string_to_sign.as_bytes()));
+
+        let mut url = format!("{}{uri}", self.config.endpoint);
+        if !query.is_empty() {
+            url.push('?');
+            url.push_str(&query);
+        }
+        let mut builder = self
+            .client
+            .request(method, url)
+            .header("x-amz-content-sha256", UNSIGNED_PAYLOAD)
+            .header("x-amz-date", datetime)
+            .header(
+                header::AUTHORIZATION,
+                format!(
+                    "{ALGORITHM} Credential={}/{scope}, SignedHeaders={signed_header_names}, Signature={signature}",
+                    self.config.access_key
+                ),
+            );
+        for (k, v) in headers {
+            builder = builder.header(*k, v);
+        }
+        builder
+    }
+
+    /// Sends a request and checks its status.
+    async fn send(&self, builder: reqwest::RequestBuilder) -> io::Result<reqwest::Response> {
+        let res = builder.send().await.map_err(io::Error::other)?;
+        match res.status() {
+            status if status.is_success() => Ok(res),
+            StatusCode::NOT_FOUND => Err(io::ErrorKind::NotFound.into()),
+            StatusCode::RANGE_NOT_SATISFIABLE => Err(io::ErrorKind::UnexpectedEof.into()),
+            status => Err(io::Error::other(format!(
+                "object storage responded with {status}: {}",
+                res.text().await.unwrap_or_default()
+            ))),
+        }
+    }
+
+    /// Converts a response into a [`ByteStream`].
+    fn stream(res: reqwest::Response) -> ByteStream {
+        Box::pin(res.bytes_stream().map_err(io::Error::other))
+    }
+}
+
+impl ResourceStore for S3 {
+    fn put<'a>(&'a self, name: &'a str, path: &'a Path) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(async move {
+            let file = tokio::fs::File::open(path).await?;
+            let len = file.metadata().await?.len();
+            let body = reqwest::Body::wrap_stream(tokio_util::io::ReaderStream::new(file));
+            self.send(
+                self.request(
+                    Method::PUT,
+                    name,
+                    &[],
+                    &[("content-length", len.to_string())],
+                )
+                .body(body),
+            )
+            .await?;
+            tokio::fs::remove_file(path).await
+        })
+    }
+
+    fn put_bytes<'a>(&'a self, name: &'a str, bytes: Bytes) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(async move {
+            self.send(
+                self.request(
+                    Method::PUT,
+                    name,
+                    &[],
+                    &[("content-length", bytes.len().to_string())],
+                )
+                .body(bytes),
+            )
+            .await
+            .map(|_| ())
+        })
+    }
+
+    fn get<'a>(&'a self, name: &'a str) -> BoxFuture<'a, io::Result<ByteStream>> {
+        Box::pin(async move {
+            self.send(self.request(Method::GET, name, &[], &[]))
+                .await
+                .map(Self::stream)
+        })
+    }
+
+    fn get_range<'a>(
+        &'a self,
+        name: &'a str,
+        range: RangeInclusive<u64>,
+    ) -> BoxFuture<'a, io::Result<ByteStream>> {
+        Box::pin(async move {
+            let value = format!("bytes={}-{}", range.start(), range.end());
+            self.send(self.request(Method::GET, name, &[], &[("range", value)]))
+                .await
+                .map(Self::stream)
+        })
+    }
+
+    fn head<'a>(&'a self, name: &'a str) -> BoxFuture<'a, io::Result<FileMeta>> {
+        Box::pin(async move {
+            let res = self
+                .send(self.request(Method::HEAD, name, &[], &[]))
+                .await?;
+            let headers = res.headers();
+            let len = headers
+                .get(header::CONTENT_LENGTH)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse().ok())
+                .ok_or_else(|| io::Error::other("missing content length"))?;
+            let modified = headers
+                .get(header::LAST_MODIFIED)
+                .and_then(|value| value.to_str().ok())
+                .and_then(crate::http_date::parse)
+                .map(SystemTime::from);
+            Ok(FileMeta {
+                name: name.to_owned(),
+                len,
+                modified,
+            })
+        })
+    }
+
+    fn delete<'a>(&'a self, name: &'a str) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(async move {
+            match self
+                .send(self.request(Method::DELETE, name, &[], &[]))
+                .await
+            {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+                _ => Ok(()),
+            }
+        })
+    }
+
+    fn list(&self) -> BoxFuture<'_, io::Result<Vec<FileMeta>>> {
+        Box::pin(async move {
+            let mut files = vec![];
+            let mut token: Option<String> = None;
+            loop {
+                let mut query = vec![("list-type", "2"), ("prefix", self.config.prefix.as_str())];
+                if let Some(ref token) = token {
+                    query.push(("continuation-token", token));
+                }
+                let body = self
+                    .send(self.request(Method::GET, "", &query, &[]))
+                    .await?
+                    .text()
+                    .await
+                    .map_err(io::Error::other)?;
+                let page = parse_list(&body, &self.config.prefix);
+                files.extend(page.files);
+                match page.next {
+                    Some(next) => token = Some(next),
+                    None => break,
+                }
+            }
+            Ok(files)
+        })
+    }
+}
+
+/// Computes HMAC-SHA256 of the data.
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("hmac accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Percent-encodes a string as required by SigV4.
+///
+/// Slashes are kept if `encode_slash` is `false`.
+fn uri_encode(value: &str, encode_slash: bool) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            b'/' if !encode_slash => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+/// A page of a `ListObjectsV2` response.
+#[derive(Debug, Default)]
+struct ListPage {
+    /// Files in this page.
+    files: Vec<FileMeta>,
+    /// Continuation token of the next page.
+    next: Option<String>,
+}
+
+/// Gets text of the first element with given tag.
+fn xml_text<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let start = xml.find(&format!("<{tag}>"))? + tag.len() + 2;
+    let end = start + xml[start..].find(&format!("</{tag}>"))?;
+    Some(&xml[start..end])
+}
+
+/// Unescapes predefined XML entities.
+fn xml_unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Parses a `ListObjectsV2` response, stripping the key prefix.
+fn parse_list(xml: &str, prefix: &str) -> ListPage {
+    let mut page = ListPage::default();
+    for content in xml.split("<Contents>").skip(1) {
+        let Some(key) = xml_text(content, "Key").map(xml_unescape) else {
+            continue;
+        };
+        let Some(name) = key.strip_prefix(prefix) else {
+            continue;
+        };
+        page.files.push(FileMeta {
+            name: name.to_owned(),
+            len: xml_text(content, "Size")
+                .and_then(|size| size.parse().ok())
+                .unwrap_or_default(),
+            modified: xml_text(content, "LastModified")
+                .and_then(|time| {
+                    OffsetDateTime::parse(time, &time::format_description::well_known::Rfc3339).ok()
+                })
+                .map(SystemTime::from),
+        });
+    }
+    if xml_text(xml, "IsTruncated") == Some("true") {
+        page.next = xml_text(xml, "NextContinuationToken").map(xml_unescape);
+    }
+    page
+}
@@ -0,0 +1,81 @@
+//! Thumbnail generation of resource payloads.
+
+use std::io::Cursor;
+
+use image::{DynamicImage, ImageFormat, ImageOutputFormat};
+
+use super::{pdf, sniff::Format};
+
+/// Available sizes of thumbnails, as pixels of the longer edge.
+pub const SIZES: [u32; 3] = [128, 256, 512];
+
+/// Default size of thumbnails.
+pub const DEFAULT_SIZE: u32 = 256;
+
+/// Quality of JPEG-encoded thumbnails.
+const QUALITY: u8 = 80;
+
+/// Maximum length of payloads to generate thumbnails from, as bytes.
+///
+/// Payloads are decoded in memory, so larger ones
+/// are left without thumbnails.
+pub const MAX_SOURCE_LEN: u64 = 32 * 1024 * 1024;
+
+/// Picks the smallest available size not less than the requested one,
+/// or the largest available size.
+#[inline]
+pub fn fit_size(requested: u32) -> u32 {
+    SIZES
+        .into_iter()
+        .find(|&s| s >= requested)
+        .unwrap_or(SIZES[SIZES.len() - 1])
+}
+
+/// Generates JPEG-encoded thumbnails of a payload in all
+/// available sizes, paired with their sizes.
+///
+/// Images are rotated by the given EXIF orientation.
+/// PDF documents are previewed by the largest JPEG image
+/// embedded in their first page.
+///
+/// Returns `None` if the payload could not be previewed.
+pub fn generate(format: Format, bytes: &[u8], orientation: u8) -> Option<Vec<(u32, Vec<u8>)>> {
+    let image = match format {
+        Format::Png => image::load_from_memory_with_format(bytes, ImageFormat::Png),
+        Format::Jpeg => image::load_from_memory_with_format(bytes, ImageFormat::Jpeg),
+        Format::WebP => image::load_from_memory_with_format(bytes, ImageFormat::WebP),
+        Format::Gif => image::load_from_memory_with_format(bytes, ImageFormat::Gif),
+        Format::Pdf => {
+            image::load_from_memory_with_format(&pdf::first_page_jpeg(bytes)?, ImageFormat::Jpeg)
+        }
+        Format::Mp4 | Format::WebM | Format::Zip => return None,
+    }
+    .ok()?;
+    let image = orient(image, orientation);
+
+    SIZES
+        .into_iter()
+        .map(|size| {
+            let mut buf = Vec::new();
+            DynamicImage::ImageRgb8(image.thumbnail(size, size).to_rgb8())
+                .write_to(&mut Cursor::new(&mut buf), ImageOutputFormat::Jpeg(QUALITY))
+                .ok()?;
+            Some((size, buf))
+        })
+        .collect()
+}
+
+/// Transforms an image by its EXIF orientation, so it
+/// could be displayed as is.
+fn orient(image: DynamicImage, orientation: u8) -> DynamicImage {
+    match orientation {
+        2 => image.fliph(),
+        3 => image.rotate180(),
+        4 => image.flipv(),
+        5 => image.rotate90().fliph(),
+        6 => image.rotate90(),
+        7 => image.rotate270().fliph(),
+        8 => image.rotate270(),
+        _ => image,
+    }
+}
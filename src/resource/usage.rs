@@ -0,0 +1,133 @@
+//! Storage usage of uploaded resources.
+
+use std::hash::{Hash, Hasher};
+
+use serde::{Deserialize, Serialize};
+
+use crate::account::{Account, Tag, TagEntry};
+
+/// Owner of a [`Usage`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum UsageOwner {
+    /// An account, with its id.
+    Account(u64),
+    /// A department, shared by all accounts in it.
+    Department(String),
+}
+
+impl UsageOwner {
+    /// Gets owners charged for resources uploaded by the given account,
+    /// including the account itself and its departments.
+    pub fn of(account: &Account) -> Vec<Self> {
+        let mut owners = vec![Self::Account(account.id())];
+        owners.extend(
+            account
+                .tags()
+                .from_entry(&TagEntry::Department)
+                .into_iter()
+                .flatten()
+                .filter_map(|tag| match tag {
+                    Tag::Department(department) => Some(Self::Department(department.to_owned())),
+                    _ => None,
+                }),
+        );
+        owners
+    }
+
+    /// Key of this owner.
+    pub fn key(&self) -> u64 {
+        match self {
+            UsageOwner::Account(id) => *id,
+            UsageOwner::Department(department) => {
+                let mut hasher = siphasher::sip::SipHasher24::new();
+                department.hash(&mut hasher);
+                hasher.finish()
+            }
+        }
+    }
+
+    /// Kind of this owner.
+    #[inline]
+    pub fn kind(&self) -> u64 {
+        match self {
+            UsageOwner::Account(_) => 0,
+            UsageOwner::Department(_) => 1,
+        }
+    }
+}
+
+/// Bytes of resources stored by an account or a department.
+///
+/// # dmds Dimensions
+///
+/// ```txt
+/// 0 -> owner key
+/// 1 -> owner kind (account -> 0, department -> 1)
+/// ```
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Usage {
+    /// Owner of this usage.
+    owner: UsageOwner,
+    /// Used bytes.
+    bytes: u64,
+}
+
+impl Usage {
+    /// Creates a new empty usage.
+    #[inline]
+    pub fn new(owner: UsageOwner) -> Self {
+        Self { owner, bytes: 0 }
+    }
+
+    /// Owner of this usage.
+    #[inline]
+    pub fn owner(&self) -> &UsageOwner {
+        &self.owner
+    }
+
+    /// Used bytes.
+    #[inline]
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    /// Charges bytes of an accepted resource.
+    #[inline]
+    pub fn charge(&mut self, bytes: u64) {
+        self.bytes = self.bytes.saturating_add(bytes)
+    }
+
+    /// Releases bytes of a destroyed resource.
+    #[inline]
+    pub fn release(&mut self, bytes: u64) {
+        self.bytes = self.bytes.saturating_sub(bytes)
+    }
+}
+
+impl dmds::Data for Usage {
+    const DIMS: usize = 2;
+    const VERSION: u32 = 1;
+
+    #[inline]
+    fn dim(&self, dim: usize) -> u64 {
+        match dim {
+            0 => self.owner.key(),
+            1 => self.owner.kind(),
+            _ => unreachable!(),
+        }
+    }
+
+    fn decode<B: bytes::Buf>(version: u32, _dims: &[u64], buf: B) -> std::io::Result<Self> {
+        match version {
+            1 => bincode::deserialize_from(buf.reader())
+                .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err)),
+            _ => unreachable!("unsupported data version {version}"),
+        }
+    }
+
+    #[inline]
+    fn encode<B: bytes::BufMut>(&self, buf: B) -> std::io::Result<()> {
+        bincode::serialize_into(buf.writer(), self)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))
+    }
+}
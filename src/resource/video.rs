@@ -0,0 +1,416 @@
+//! Container probing of video payloads.
+//!
+//! Only headers of MP4 and WebM containers are parsed,
+//! without decoding any frame. Other parts of a payload
+//! are skipped by seeking, so they are never read.
+
+use std::io::{Read, Seek, SeekFrom};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    meta::{be_u16, be_u32, be_u64},
+    sniff::Format,
+};
+
+/// Metadata of a video.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VideoMeta {
+    /// Duration of the video, as milliseconds.
+    ///
+    /// This is `None` if the container doesn't declare it,
+    /// like WebM files recorded by browsers.
+    pub duration: Option<u64>,
+    /// Width of the video, as pixels.
+    pub width: u32,
+    /// Height of the video, as pixels.
+    pub height: u32,
+    /// Codec of the video track.
+    pub codec: VideoCodec,
+    /// Whether screen players could decode the video.
+    pub playable: bool,
+}
+
+/// Codec of a video track.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum VideoCodec {
+    /// H.264, or AVC.
+    H264,
+    /// H.265, or HEVC.
+    H265,
+    /// VP8.
+    Vp8,
+    /// VP9.
+    Vp9,
+    /// AV1.
+    Av1,
+    /// Other codecs, with the identifier in the container.
+    Other(String),
+}
+
+impl VideoCodec {
+    /// Whether screen players could decode this codec.
+    #[inline]
+    pub fn is_playable(&self) -> bool {
+        matches!(self, VideoCodec::H264 | VideoCodec::Vp8 | VideoCodec::Vp9)
+    }
+
+    /// Gets the codec from the four-character code of
+    /// an MP4 sample entry.
+    fn from_fourcc(fourcc: &[u8]) -> Self {
+        match fourcc {
+            b"avc1" | b"avc3" => VideoCodec::H264,
+            b"hvc1" | b"hev1" => VideoCodec::H265,
+            b"vp08" => VideoCodec::Vp8,
+            b"vp09" => VideoCodec::Vp9,
+            b"av01" => VideoCodec::Av1,
+            _ => VideoCodec::Other(String::from_utf8_lossy(fourcc).into_owned()),
+        }
+    }
+
+    /// Gets the codec from the codec id of a Matroska track.
+    fn from_codec_id(id: &[u8]) -> Self {
+        match id {
+            b"V_MPEG4/ISO/AVC" => VideoCodec::H264,
+            b"V_MPEGH/ISO/HEVC" => VideoCodec::H265,
+            b"V_VP8" => VideoCodec::Vp8,
+            b"V_VP9" => VideoCodec::Vp9,
+            b"V_AV1" => VideoCodec::Av1,
+            _ => VideoCodec::Other(String::from_utf8_lossy(id).into_owned()),
+        }
+    }
+}
+
+impl VideoMeta {
+    /// Maximum length of a header read into memory, like
+    /// the `moov` box of MP4 files, as bytes.
+    pub const MAX_HEADER_LEN: u64 = 16 * 1024 * 1024;
+
+    /// Probes metadata of a video with given format from the reader.
+    ///
+    /// Returns `None` if the format is not a video format,
+    /// the container is malformed, or there is no video track.
+    pub fn probe<R: Read + Seek>(format: Format, mut reader: R) -> Option<Self> {
+        let (duration, width, height, codec) = match format {
+            Format::Mp4 => mp4_probe(&mp4_read_moov(&mut reader)?)?,
+            Format::WebM => webm_probe(&mut reader)?,
+            _ => return None,
+        };
+        Some(Self {
+            duration: duration.filter(|&d| d > 0),
+            width,
+            height,
+            playable: codec.is_playable(),
+            codec,
+        })
+    }
+
+    /// Duration of the video rounded up to seconds.
+    #[inline]
+    pub fn duration_secs(&self) -> Option<u32> {
+        self.duration
+            .map(|ms| ms.div_ceil(1000).try_into().unwrap_or(u32::MAX))
+    }
+}
+
+/// Probed properties of a video, as duration in milliseconds,
+/// width, height and codec.
+type Probed = (Option<u64>, u32, u32, VideoCodec);
+
+/// Iterates over ISO base media file format boxes,
+/// as box types and contents.
+fn mp4_boxes(bytes: &[u8]) -> impl Iterator<Item = (&[u8], &[u8])> {
+    let mut offset = 0;
+    std::iter::from_fn(move || {
+        let size = be_u32(bytes, offset)? as u64;
+        let ty = bytes.get(offset + 4..offset + 8)?;
+        let (header, size) = match size {
+            // Extends to the end of the enclosing box.
+            0 => (8, (bytes.len() - offset) as u64),
+            1 => (16, be_u64(bytes, offset + 8)?),
+            _ => (8, size),
+        };
+        let end = offset.checked_add(usize::try_from(size).ok()?)?;
+        let content = bytes.get(offset + header..end)?;
+        offset = end;
+        Some((ty, content))
+    })
+}
+
+/// Finds the first box with given type.
+#[inline]
+fn mp4_find<'a>(bytes: &'a [u8], ty: &[u8; 4]) -> Option<&'a [u8]> {
+    mp4_boxes(bytes)
+        .find(|(t, _)| *t == ty)
+        .map(|(_, content)| content)
+}
+
+/// Reads content of the top-level `moov` box of an MP4 file,
+/// skipping other boxes like `mdat`.
+fn mp4_read_moov<R: Read + Seek>(reader: &mut R) -> Option<Vec<u8>> {
+    loop {
+        let mut header = [0; 8];
+        reader.read_exact(&mut header).ok()?;
+        let size = be_u32(&header, 0)? as u64;
+        let len = match size {
+            // Extends to the end of the file.
+            0 => None,
+            1 => {
+                let mut large = [0; 8];
+                reader.read_exact(&mut large).ok()?;
+                Some(u64::from_be_bytes(large).checked_sub(16)?)
+            }
+            _ => Some(size.checked_sub(8)?),
+        };
+        if &header[4..] == b"moov" {
+            let mut moov = vec![];
+            reader
+                .by_ref()
+                .take(len.unwrap_or(u64::MAX).min(VideoMeta::MAX_HEADER_LEN + 1))
+                .read_to_end(&mut moov)
+                .ok()?;
+            return (moov.len() as u64 <= VideoMeta::MAX_HEADER_LEN
+                && len.map_or(true, |len| moov.len() as u64 == len))
+            .then_some(moov);
+        }
+        reader
+            .seek(SeekFrom::Current(i64::try_from(len?).ok()?))
+            .ok()?;
+    }
+}
+
+/// Probes an MP4 video from content of its `moov` box.
+fn mp4_probe(moov: &[u8]) -> Option<Probed> {
+    let mvhd = mp4_find(moov, b"mvhd")?;
+    let (timescale, mut duration) = match mvhd.first()? {
+        0 => (be_u32(mvhd, 12)?, be_u32(mvhd, 16)? as u64),
+        1 => (be_u32(mvhd, 20)?, be_u64(mvhd, 24)?),
+        _ => return None,
+    };
+    // Fragmented files declare the duration in the movie extends box.
+    if duration == 0 {
+        if let Some(mehd) = mp4_find(moov, b"mvex").and_then(|mvex| mp4_find(mvex, b"mehd")) {
+            duration = match mehd.first()? {
+                0 => be_u32(mehd, 4)? as u64,
+                _ => be_u64(mehd, 4)?,
+            };
+        }
+    }
+    let duration = (timescale > 0)
+        .then(|| {
+            (duration as u128 * 1000 / timescale as u128)
+                .try_into()
+                .ok()
+        })
+        .flatten();
+
+    let (width, height, codec) =
+        mp4_boxes(moov)
+            .filter(|(ty, _)| *ty == b"trak")
+            .find_map(|(_, trak)| {
+                let mdia = mp4_find(trak, b"mdia")?;
+                if mp4_find(mdia, b"hdlr")?.get(8..12)? != b"vide" {
+                    return None;
+                }
+                let stsd = mp4_find(mdia, b"minf")
+                    .and_then(|minf| mp4_find(minf, b"stbl"))
+                    .and_then(|stbl| mp4_find(stbl, b"stsd"))?;
+                // Skip version, flags and entry count of the sample description.
+                let (fourcc, entry) = mp4_boxes(stsd.get(8..)?).next()?;
+
+                // Presentation size in 16.16 fixed-point, at the end of the track header.
+                let tkhd = mp4_find(trak, b"tkhd")?;
+                let (mut width, mut height) = (
+                    be_u32(tkhd, tkhd.len().checked_sub(8)?)? >> 16,
+                    be_u32(tkhd, tkhd.len() - 4)? >> 16,
+                );
+                if width == 0 || height == 0 {
+                    // Coded size in the visual sample entry.
+                    width = be_u16(entry, 24)? as u32;
+                    height = be_u16(entry, 26)? as u32;
+                }
+                Some((width, height, VideoCodec::from_fourcc(fourcc)))
+            })?;
+    Some((duration, width, height, codec))
+}
+
+/// Reads a variable-length integer of EBML at given offset,
+/// as the value and its length.
+///
+/// The length marker is kept if `marker` is `true`, as
+/// element ids do.
+fn ebml_vint(bytes: &[u8], offset: usize, marker: bool) -> Option<(u64, usize)> {
+    let first = *bytes.get(offset)?;
+    let len = first.leading_zeros() as usize + 1;
+    if len > 8 {
+        return None;
+    }
+    let mut value = if marker {
+        first as u64
+    } else {
+        first as u64 & (0xff >> len)
+    };
+    for &b in bytes.get(offset + 1..offset + len)? {
+        value = value << 8 | b as u64;
+    }
+    Some((value, len))
+}
+
+/// Iterates over EBML elements, as element ids and contents.
+fn ebml_elements(bytes: &[u8]) -> impl Iterator<Item = (u64, &[u8])> {
+    let mut offset = 0;
+    std::iter::from_fn(move || {
+        let (id, id_len) = ebml_vint(bytes, offset, true)?;
+        let (size, size_len) = ebml_vint(bytes, offset + id_len, false)?;
+        let start = offset + id_len + size_len;
+        let end = if size == (1 << (7 * size_len)) - 1 {
+            // Unknown size, which extends to the end of the parent.
+            bytes.len()
+        } else {
+            start.checked_add(usize::try_from(size).ok()?)?
+        };
+        let content = bytes.get(start..end.min(bytes.len()))?;
+        offset = end;
+        Some((id, content))
+    })
+}
+
+/// Finds the first element with given id.
+#[inline]
+fn ebml_find(bytes: &[u8], id: u64) -> Option<&[u8]> {
+    ebml_elements(bytes)
+        .find(|&(i, _)| i == id)
+        .map(|(_, content)| content)
+}
+
+/// Reads a variable-length integer of EBML from the reader,
+/// as the value and its length.
+fn ebml_read_vint<R: Read>(reader: &mut R, marker: bool) -> Option<(u64, usize)> {
+    let mut buf = [0; 8];
+    reader.read_exact(&mut buf[..1]).ok()?;
+    let len = buf[0].leading_zeros() as usize + 1;
+    reader.read_exact(buf.get_mut(1..len)?).ok()?;
+    ebml_vint(&buf[..len], 0, marker)
+}
+
+/// Reads the header of an EBML element from the reader,
+/// as the element id and content size.
+///
+/// The size is `None` if it's unknown.
+fn ebml_read_header<R: Read>(reader: &mut R) -> Option<(u64, Option<u64>)> {
+    let (id, _) = ebml_read_vint(reader, true)?;
+    let (size, len) = ebml_read_vint(reader, false)?;
+    Some((id, (size != (1 << (7 * len)) - 1).then_some(size)))
+}
+
+/// Reads content of an EBML element with given size from the reader.
+fn ebml_read_content<R: Read>(reader: &mut R, size: Option<u64>) -> Option<Vec<u8>> {
+    let size = size.filter(|&size| size <= VideoMeta::MAX_HEADER_LEN)?;
+    let mut content = vec![0; size as usize];
+    reader.read_exact(&mut content).ok()?;
+    Some(content)
+}
+
+/// Reads content of an unsigned integer element.
+fn ebml_uint(content: &[u8]) -> Option<u64> {
+    (content.len() <= 8).then(|| content.iter().fold(0, |v, &b| v << 8 | b as u64))
+}
+
+/// Reads content of a float element.
+fn ebml_float(content: &[u8]) -> Option<f64> {
+    match content.len() {
+        4 => Some(f32::from_be_bytes(content.try_into().ok()?) as f64),
+        8 => Some(f64::from_be_bytes(content.try_into().ok()?)),
+        _ => None,
+    }
+}
+
+/// Probes a WebM video from its segment information and tracks,
+/// skipping other elements.
+fn webm_probe<R: Read + Seek>(reader: &mut R) -> Option<Probed> {
+    /// Ids of used elements.
+    mod id {
+        /// The root segment.
+        pub const SEGMENT: u64 = 0x18538067;
+        /// Segment information.
+        pub const INFO: u64 = 0x1549a966;
+        /// Nanoseconds of a timestamp unit.
+        pub const TIMESTAMP_SCALE: u64 = 0x2ad7b1;
+        /// Duration of the segment, in timestamp units.
+        pub const DURATION: u64 = 0x4489;
+        /// Tracks of the segment.
+        pub const TRACKS: u64 = 0x1654ae6b;
+        /// A track.
+        pub const TRACK_ENTRY: u64 = 0xae;
+        /// Type of a track.
+        pub const TRACK_TYPE: u64 = 0x83;
+        /// Codec of a track.
+        pub const CODEC_ID: u64 = 0x86;
+        /// Video settings of a track.
+        pub const VIDEO: u64 = 0xe0;
+        /// Width of the encoded video frames.
+        pub const PIXEL_WIDTH: u64 = 0xb0;
+        /// Height of the encoded video frames.
+        pub const PIXEL_HEIGHT: u64 = 0xba;
+        /// A cluster of blocks.
+        pub const CLUSTER: u64 = 0x1f43b675;
+    }
+    /// Track type of video tracks.
+    const VIDEO_TRACK: u64 = 1;
+
+    // Skip the EBML header, and elements before the segment.
+    loop {
+        match ebml_read_header(reader)? {
+            (id::SEGMENT, _) => break,
+            (_, size) => {
+                reader
+                    .seek(SeekFrom::Current(i64::try_from(size?).ok()?))
+                    .ok()?;
+            }
+        }
+    }
+    let mut duration = None;
+    let mut track = None;
+    // Info and tracks precede clusters, which may have unknown sizes.
+    while let Some((i, size)) = ebml_read_header(reader) {
+        match i {
+            id::INFO => {
+                let content = ebml_read_content(reader, size)?;
+                let scale = ebml_find(&content, id::TIMESTAMP_SCALE)
+                    .and_then(ebml_uint)
+                    .unwrap_or(1_000_000);
+                duration = ebml_find(&content, id::DURATION)
+                    .and_then(ebml_float)
+                    .filter(|d| d.is_finite() && *d >= 0.0)
+                    .map(|d| (d * scale as f64 / 1_000_000.0).round() as u64);
+            }
+            id::TRACKS => {
+                let content = ebml_read_content(reader, size)?;
+                track = ebml_elements(&content)
+                    .filter(|&(i, _)| i == id::TRACK_ENTRY)
+                    .find_map(|(_, entry)| {
+                        if ebml_find(entry, id::TRACK_TYPE).and_then(ebml_uint)? != VIDEO_TRACK {
+                            return None;
+                        }
+                        let video = ebml_find(entry, id::VIDEO)?;
+                        Some((
+                            ebml_find(video, id::PIXEL_WIDTH).and_then(ebml_uint)? as u32,
+                            ebml_find(video, id::PIXEL_HEIGHT).and_then(ebml_uint)? as u32,
+                            VideoCodec::from_codec_id(ebml_find(entry, id::CODEC_ID)?),
+                        ))
+                    });
+            }
+            id::CLUSTER => break,
+            _ => {
+                let Some(size) = size.and_then(|size| i64::try_from(size).ok()) else {
+                    break;
+                };
+                if reader.seek(SeekFrom::Current(size)).is_err() {
+                    break;
+                }
+            }
+        }
+    }
+    let (width, height, codec) = track?;
+    Some((duration, width, height, codec))
+}
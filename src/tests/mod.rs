@@ -8,10 +8,15 @@ use tokio::sync::Mutex;
 use crate::Global;
 
 fn router() -> (Global<MemStorage>, Router) {
+    router_with(|_| {})
+}
+
+/// Creates a router with the test configuration modified by `configure`.
+fn router_with(configure: impl FnOnce(&mut Config)) -> (Global<MemStorage>, Router) {
     sms4_backend::IS_TEST.store(true, std::sync::atomic::Ordering::Release);
     use lettre::transport::smtp::authentication::Mechanism;
 
-    let config = Config {
+    let mut config = Config {
         smtp: sms4_backend::config::SMTP {
             server: "smtp-mail.outlook.com".to_owned(),
             port: Some(587),
@@ -25,7 +30,13 @@ fn router() -> (Global<MemStorage>, Router) {
         port: 8080,
         resource_path: PathBuf::from(".test/resources"),
         screens: 2,
+        categories: vec!["club".to_owned(), "academic".to_owned()],
+        post_max_dur: Default::default(),
+        quota: Default::default(),
+        payload_limit: Default::default(),
+        resource_store: sms4_backend::resource::store::StoreConfig::Memory,
     };
+    configure(&mut config);
     let state = Global {
         smtp_transport: Arc::new(config.smtp.to_transport().unwrap()),
         worlds: Arc::new(crate::Worlds {
@@ -41,14 +52,19 @@ fn router() -> (Global<MemStorage>, Router) {
             resource: Arc::new(world!(
                 MemStorage::new(),
                 ipc!(256) => ..,
-                1 => ..2
+                1 => ..=255,
+                ipc!(256) => ..,
+                ipc!(16) => ..
             )),
             notification: Arc::new(world! {
                 MemStorage::new(),
                 ipc!(32) => ..,
                 368 / 4 => ..=367
             }),
+            usage: Arc::new(world!(MemStorage::new(), ipc!(16) => .., 1 => ..2)),
         }),
+        resource_store: config.resource_store.build(&config.resource_path),
+        resource_locks: Default::default(),
         config: Arc::new(config),
         test_cx: Default::default(),
         resource_sessions: Arc::new(Mutex::new(sms4_backend::resource::UploadSessions::new())),
@@ -181,3 +197,7 @@ macro_rules! p_json {
 }
 
 mod account;
+mod post;
+mod resource;
+mod stats;
+mod store;
@@ -0,0 +1,526 @@
+use axum::http::StatusCode;
+use serde_json::json;
+use sms4_backend::{
+    account::Account,
+    post::{Post, Priority, Status},
+    Id,
+};
+use time::{Duration, OffsetDateTime};
+
+use crate::{
+    gd,
+    routes::*,
+    sd,
+    tests::{router, router_with},
+    Auth,
+};
+
+#[tokio::test]
+async fn expire_pending() {
+    let (state, _) = router();
+    let today = OffsetDateTime::now_utc().date();
+    let stale = Post::new(
+        "Stale".to_owned(),
+        String::new(),
+        (today - Duration::DAY)..=(today + Duration::DAY),
+        Box::new([]),
+        1,
+        false,
+        Priority::Normal,
+        Post::MAX_DUR,
+    )
+    .unwrap();
+    let fresh = Post::new(
+        "Fresh".to_owned(),
+        String::new(),
+        (today + Duration::DAY)..=(today + Duration::DAY * 2),
+        Box::new([]),
+        1,
+        false,
+        Priority::Normal,
+        Post::MAX_DUR,
+    )
+    .unwrap();
+    let (stale_id, fresh_id) = (stale.id(), fresh.id());
+    state.worlds.post.insert(stale).await.unwrap();
+    state.worlds.post.insert(fresh).await.unwrap();
+
+    assert_eq!(crate::job::expire_pending_posts(&state).await.unwrap(), 1);
+    {
+        let select = sd!(state.worlds.post, stale_id);
+        let lazy = gd!(select, stale_id).unwrap();
+        let post = lazy.get().await.unwrap();
+        assert_eq!(post.state().status(), Status::Rejected);
+        assert_eq!(
+            post.state().operator(),
+            sms4_backend::post::State::SYSTEM_OPERATOR
+        );
+    }
+    let select = sd!(state.worlds.post, fresh_id);
+    let lazy = gd!(select, fresh_id).unwrap();
+    assert_eq!(lazy.get().await.unwrap().state().status(), Status::Pending);
+}
+
+#[tokio::test]
+async fn bulk_review() {
+    let (state, route) = router();
+    let mut account: Account = acc_exp!(DCK, ReviewPost);
+    let (token, _) = account.login("shanlilinghuo").unwrap();
+    let id = account.id();
+    state.worlds.account.insert(account).await.unwrap();
+
+    let today = OffsetDateTime::now_utc().date();
+    let post = Post::new(
+        "Club".to_owned(),
+        String::new(),
+        today..=(today + Duration::DAY),
+        Box::new([]),
+        1,
+        false,
+        Priority::Normal,
+        Post::MAX_DUR,
+    )
+    .unwrap();
+    let post_id = post.id();
+    state.worlds.post.insert(post).await.unwrap();
+
+    let res = req!(route, PATCH => BULK_REVIEW_POST,
+        Auth { account: id, token },
+        json!({
+            "reviews": [
+                { "post": post_id, "status": "Approved" },
+                { "post": post_id, "status": "Approved" },
+                { "post": post_id.wrapping_add(1), "status": "Rejected" },
+            ],
+        }) => json
+    );
+    assert!(res.status().is_success());
+    let res: serde_json::Value = p_json!(res);
+    let results = res["results"].as_array().unwrap();
+    assert_eq!(results.len(), 3);
+    assert!(results[0].get("error").is_none());
+    assert!(results[1].get("error").is_some());
+    assert!(results[2].get("error").is_some());
+
+    let select = sd!(state.worlds.post, post_id);
+    let lazy = gd!(select, post_id).unwrap();
+    assert_eq!(lazy.get().await.unwrap().state().status(), Status::Approved);
+}
+
+#[tokio::test]
+async fn post_categories() {
+    use sms4_backend::resource::{Resource, Variant};
+
+    let (state, route) = router();
+    let mut account: Account = acc_exp!(DCK, Post);
+    let (token, _) = account.login("shanlilinghuo").unwrap();
+    let id = account.id();
+    state.worlds.account.insert(account).await.unwrap();
+    let resource = Resource::new(Variant::Video { duration: 60 }, Id(id));
+    let resource_id = resource.id();
+    state.worlds.resource.insert(resource).await.unwrap();
+
+    let today = OffsetDateTime::now_utc().date();
+    let new_post = |categories: &[&str]| {
+        json!({
+            "title": "Club",
+            "notes": "",
+            "time": { "start": today, "end": today + Duration::DAY },
+            "resources": [resource_id],
+            "grouped": false,
+            "priority": "Normal",
+            "categories": categories,
+        })
+    };
+    let res = req!(route, PUT => NEW_POST,
+        Auth { account: id, token: token.clone() },
+        new_post(&["club", "gaming"]) => json
+    );
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let res = req!(route, PUT => NEW_POST,
+        Auth { account: id, token: token.clone() },
+        new_post(&["club"]) => json
+    );
+    assert!(res.status().is_success());
+    let res: serde_json::Value = p_json!(res);
+    let post_id: u64 = res["id"].as_str().unwrap().parse().unwrap();
+
+    let res = req!(route, PATCH => format!("/post/modify/{post_id}"),
+        Auth { account: id, token: token.clone() },
+        json!({ "categories": ["gaming"] }) => json
+    );
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let res = req!(route, PATCH => format!("/post/modify/{post_id}"),
+        Auth { account: id, token },
+        json!({ "categories": ["academic"] }) => json
+    );
+    assert!(res.status().is_success());
+
+    let select = sd!(state.worlds.post, post_id);
+    let lazy = gd!(select, post_id).unwrap();
+    assert_eq!(lazy.get().await.unwrap().categories(), ["academic"]);
+}
+
+#[tokio::test]
+async fn shared_resources() {
+    use sms4_backend::resource::{Resource, Variant};
+
+    let (state, route) = router();
+    let mut account: Account = acc_exp!(DCK, Post);
+    let (token, _) = account.login("shanlilinghuo").unwrap();
+    let id = account.id();
+    state.worlds.account.insert(account).await.unwrap();
+    let shared = Resource::new(Variant::Video { duration: 60 }, Id(id));
+    let others = Resource::new(Variant::Video { duration: 60 }, Id(id.wrapping_add(1)));
+    let (shared_id, others_id) = (shared.id(), others.id());
+    state.worlds.resource.insert(shared).await.unwrap();
+    state.worlds.resource.insert(others).await.unwrap();
+
+    let today = OffsetDateTime::now_utc().date();
+    let new_post = |resources: &[u64]| {
+        json!({
+            "title": "Club",
+            "notes": "",
+            "time": { "start": today, "end": today + Duration::DAY },
+            "resources": resources,
+            "grouped": false,
+            "priority": "Normal",
+        })
+    };
+    let ref_count = |id: u64| {
+        let state = state.clone();
+        async move {
+            let select = sd!(state.worlds.resource, id);
+            let lazy = gd!(select, id)?;
+            Some(lazy.get().await.unwrap().ref_count())
+        }
+    };
+
+    // resources of others are rejected without blocking the valid ones
+    let res = req!(route, PUT => NEW_POST,
+        Auth { account: id, token: token.clone() },
+        new_post(&[shared_id, others_id]) => json
+    );
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    assert_eq!(ref_count(shared_id).await, Some(0));
+
+    let mut posts = vec![];
+    for _ in 0..2 {
+        let res = req!(route, PUT => NEW_POST,
+            Auth { account: id, token: token.clone() },
+            new_post(&[shared_id]) => json
+        );
+        assert!(res.status().is_success());
+        let res: serde_json::Value = p_json!(res);
+        posts.push(res["id"].as_str().unwrap().parse::<u64>().unwrap());
+    }
+    assert_eq!(ref_count(shared_id).await, Some(2));
+
+    let res = req!(route, PATCH => format!("/post/modify/{}", posts[0]),
+        Auth { account: id, token: token.clone() },
+        json!({ "resources": [shared_id, others_id] }) => json
+    );
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    assert_eq!(ref_count(others_id).await, Some(0));
+
+    let res = req!(route, DELETE => format!("/post/delete/{}", posts[0]),
+        Auth { account: id, token: token.clone() }
+    );
+    assert!(res.status().is_success());
+    assert_eq!(ref_count(shared_id).await, Some(1));
+
+    let res = req!(route, DELETE => format!("/post/delete/{}", posts[1]),
+        Auth { account: id, token }
+    );
+    assert!(res.status().is_success());
+    assert_eq!(ref_count(shared_id).await, None);
+}
+
+#[tokio::test]
+async fn post_max_dur_override() {
+    use sms4_backend::{
+        account::Tag,
+        config::PostMaxDurOverride,
+        resource::{Resource, Variant},
+    };
+
+    let (state, route) = router_with(|config| {
+        config.post_max_dur.overrides.push(PostMaxDurOverride {
+            tag: Tag::Department("SubIT".to_owned()),
+            days: 14,
+        })
+    });
+    let today = OffsetDateTime::now_utc().date();
+    let new_post = |resource: u64, days: i64| {
+        json!({
+            "title": "Club",
+            "notes": "",
+            "time": { "start": today, "end": today + Duration::days(days) },
+            "resources": [resource],
+            "grouped": false,
+            "priority": "Normal",
+        })
+    };
+    let mut account: Account = acc_exp!(DCK, Post);
+    let (token, _) = account.login("shanlilinghuo").unwrap();
+    let id = account.id();
+    state.worlds.account.insert(account).await.unwrap();
+    let mut another: Account = acc_exp!(MYG, Post);
+    let (another_token, _) = another.login("123456").unwrap();
+    let another_id = another.id();
+    state.worlds.account.insert(another).await.unwrap();
+    let resource = Resource::new(Variant::Video { duration: 60 }, Id(id));
+    let another_resource = Resource::new(Variant::Video { duration: 60 }, Id(another_id));
+    let (resource_id, another_resource_id) = (resource.id(), another_resource.id());
+    state.worlds.resource.insert(resource).await.unwrap();
+    state
+        .worlds
+        .resource
+        .insert(another_resource)
+        .await
+        .unwrap();
+
+    // the default limit applies to accounts without overriding tags
+    let res = req!(route, PUT => NEW_POST,
+        Auth { account: another_id, token: another_token },
+        new_post(another_resource_id, 10) => json
+    );
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    let res = req!(route, PUT => NEW_POST,
+        Auth { account: id, token: token.clone() },
+        new_post(resource_id, 15) => json
+    );
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    let res = req!(route, PUT => NEW_POST,
+        Auth { account: id, token: token.clone() },
+        new_post(resource_id, 10) => json
+    );
+    assert!(res.status().is_success());
+    let res: serde_json::Value = p_json!(res);
+    let post_id: u64 = res["id"].as_str().unwrap().parse().unwrap();
+
+    let res = req!(route, PATCH => format!("/post/modify/{post_id}"),
+        Auth { account: id, token: token.clone() },
+        json!({ "time": { "start": today, "end": today + Duration::days(15) } }) => json
+    );
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    let res = req!(route, PATCH => format!("/post/modify/{post_id}"),
+        Auth { account: id, token },
+        json!({ "time": { "start": today, "end": today + Duration::days(14) } }) => json
+    );
+    assert!(res.status().is_success());
+
+    let select = sd!(state.worlds.post, post_id);
+    let lazy = gd!(select, post_id).unwrap();
+    assert_eq!(
+        *lazy.get().await.unwrap().time().end(),
+        today + Duration::days(14)
+    );
+}
+
+#[test]
+fn sort_cursor() {
+    use crate::handle::Cursor;
+
+    let cursor: Cursor = serde_json::from_str(r#""2460311:1234567890""#).unwrap();
+    assert_eq!(
+        cursor,
+        Cursor {
+            key: 2460311,
+            id: 1234567890
+        }
+    );
+    assert_eq!(
+        serde_json::to_string(&cursor).unwrap(),
+        r#""2460311:1234567890""#
+    );
+    let cursor: Cursor = serde_json::from_str(r#""-1:0""#).unwrap();
+    assert_eq!(cursor, Cursor { key: -1, id: 0 });
+
+    for invalid in ["", "2460311", "2460311:", ":1", "a:1", "1:-1", "1:2:3"] {
+        assert!(serde_json::from_value::<Cursor>(json!(invalid)).is_err());
+    }
+}
+
+#[test]
+fn sort_pages() {
+    use crate::handle::{sort_page, Cursor, Order};
+
+    let items = vec![(2, 1), (1, 3), (1, 2), (3, 0)];
+
+    let mut page = items.clone();
+    let next = sort_page(&mut page, Order::Asc, None, 2);
+    assert_eq!(page, [(1, 2), (1, 3)]);
+    assert_eq!(next, Some(Cursor { key: 1, id: 3 }));
+    let mut page = items.clone();
+    assert_eq!(sort_page(&mut page, Order::Asc, next, 2), None);
+    assert_eq!(page, [(2, 1), (3, 0)]);
+
+    let mut page = items.clone();
+    let next = sort_page(&mut page, Order::Desc, None, 3);
+    assert_eq!(page, [(3, 0), (2, 1), (1, 3)]);
+    assert_eq!(next, Some(Cursor { key: 1, id: 3 }));
+    let mut page = items.clone();
+    assert_eq!(sort_page(&mut page, Order::Desc, next, 3), None);
+    assert_eq!(page, [(1, 2)]);
+
+    // cursors after the last item
+    let mut page = items;
+    let cursor = Cursor { key: 3, id: 0 };
+    assert_eq!(sort_page(&mut page, Order::Asc, Some(cursor), 2), None);
+    assert!(page.is_empty());
+}
+
+#[tokio::test]
+async fn sorted_filter() {
+    let (state, route) = router();
+    let mut account: Account = acc_exp!(DCK, Post);
+    let (token, _) = account.login("shanlilinghuo").unwrap();
+    let id = account.id();
+    state.worlds.account.insert(account).await.unwrap();
+
+    // posts starting on following days
+    let today = OffsetDateTime::now_utc().date();
+    let mut posts = vec![];
+    for days in 0..3 {
+        let start = today + Duration::days(days);
+        let post = Post::new(
+            "Club".to_owned(),
+            String::new(),
+            start..=(start + Duration::DAY),
+            Box::new([]),
+            id,
+            false,
+            Priority::Normal,
+            Post::MAX_DUR,
+        )
+        .unwrap();
+        posts.push(post.id().to_string());
+        state.worlds.post.insert(post).await.unwrap();
+    }
+    let filter = |query: String| {
+        let route = route.clone();
+        let token = token.clone();
+        async move {
+            let res = req!(route, GET => format!("/post/filter?sort=Start&limit=2{query}"),
+                Auth { account: id, token }
+            );
+            assert!(res.status().is_success());
+            let res: serde_json::Value = p_json!(res);
+            let ids: Vec<String> = res["posts"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|id| id.as_str().unwrap().to_owned())
+                .collect();
+            (ids, res["next"].as_str().map(str::to_owned))
+        }
+    };
+
+    let (page, next) = filter(String::new()).await;
+    assert_eq!(page, posts[..2]);
+    let (page, next) = filter(format!("&cursor={}", next.unwrap())).await;
+    assert_eq!(page, posts[2..]);
+    assert_eq!(next, None);
+
+    let (page, next) = filter("&order=Desc".to_owned()).await;
+    assert_eq!(page, [posts[2].clone(), posts[1].clone()]);
+    let (page, next) = filter(format!("&order=Desc&cursor={}", next.unwrap())).await;
+    assert_eq!(page, posts[..1]);
+    assert_eq!(next, None);
+
+    let res = req!(route, GET => "/post/filter?sort=Start&cursor=invalid",
+        Auth { account: id, token }
+    );
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn filter_expand() {
+    use sms4_backend::account::{Permission, Tag};
+
+    let (state, route) = router();
+    let mut account: Account = acc_exp!(DCK, Post);
+    let (token, _) = account.login("shanlilinghuo").unwrap();
+    let id = account.id();
+    state.worlds.account.insert(account).await.unwrap();
+    let mut another: Account = acc_exp!(MYG, GetPubPost);
+    let (another_token, _) = another.login("123456").unwrap();
+    let another_id = another.id();
+    state.worlds.account.insert(another).await.unwrap();
+
+    // posts available from tomorrow
+    let tomorrow = OffsetDateTime::now_utc().date() + Duration::DAY;
+    let new_post = || {
+        Post::new(
+            "Club".to_owned(),
+            String::new(),
+            tomorrow..=(tomorrow + Duration::DAY),
+            Box::new([]),
+            id,
+            false,
+            Priority::Normal,
+            Post::MAX_DUR,
+        )
+        .unwrap()
+    };
+    let mut approved = new_post();
+    approved
+        .pust_state(sms4_backend::post::State::system(
+            Status::Approved,
+            String::new(),
+        ))
+        .unwrap();
+    let pending = new_post();
+    let (approved_id, pending_id) = (approved.id().to_string(), pending.id().to_string());
+    state.worlds.post.insert(approved).await.unwrap();
+    state.worlds.post.insert(pending).await.unwrap();
+
+    let filter = |account: u64, token: String| {
+        let route = route.clone();
+        async move {
+            let res = req!(route, GET => format!("/post/filter?on={tomorrow}&expand=true"),
+                Auth { account, token }
+            );
+            assert!(res.status().is_success());
+            let res: serde_json::Value = p_json!(res);
+            let mut posts: Vec<String> = res["posts"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|id| id.as_str().unwrap().to_owned())
+                .collect();
+            posts.sort_unstable();
+            (posts, res["infos"].clone())
+        }
+    };
+    let mut both = vec![approved_id.clone(), pending_id.clone()];
+    both.sort_unstable();
+
+    // approved posts on other days are listed for screens in brief
+    let (posts, infos) = filter(another_id, another_token.clone()).await;
+    assert_eq!(posts, [approved_id.clone()]);
+    assert_eq!(infos[&approved_id]["type"], "Simple");
+    assert!(infos[&approved_id].get("states").is_none());
+
+    let (posts, infos) = filter(id, token).await;
+    assert_eq!(posts, both);
+    assert_eq!(infos[&approved_id]["type"], "Full");
+    assert_eq!(infos[&pending_id]["type"], "Full");
+
+    {
+        let select = sd!(state.worlds.account, another_id);
+        let mut lazy = gd!(select, another_id).unwrap();
+        lazy.get_mut()
+            .await
+            .unwrap()
+            .tags_mut()
+            .insert(Tag::Permission(Permission::ReviewPost));
+        lazy.close().await.unwrap();
+    }
+    let (posts, infos) = filter(another_id, another_token).await;
+    assert_eq!(posts, both);
+    assert_eq!(infos[&approved_id]["type"], "Full");
+    assert_eq!(infos[&pending_id]["type"], "Full");
+}
@@ -0,0 +1,1298 @@
+use std::{path::PathBuf, time::SystemTime};
+
+use axum::{http::StatusCode, Router};
+use serde_json::json;
+use sms4_backend::{
+    account::Account,
+    post::{Post, Priority, State, Status},
+    resource::{Resource, TextStyle, UploadSessions, Variant},
+    Error, Id,
+};
+use time::{Duration, OffsetDateTime};
+
+use crate::{
+    gd,
+    routes::*,
+    sd,
+    tests::{router, router_with},
+    Auth,
+};
+
+#[test]
+fn resume_upload_session() {
+    let mut sessions = UploadSessions::new();
+    let resource = Resource::new(Variant::Video { duration: 60 }, Id(1));
+    let id = resource.id();
+    sessions.insert(resource);
+
+    assert!(matches!(
+        sessions.begin_write(id, Id(2), 0),
+        Err(Error::PermissionDenied)
+    ));
+    sessions.begin_write(id, Id(1), 0).unwrap();
+    assert!(matches!(
+        sessions.begin_write(id, Id(1), 0),
+        Err(Error::ResourceUploadBusy(_))
+    ));
+    sessions.end_write(id, Some(1024));
+    assert_eq!(sessions.received(id, Id(1)).unwrap(), 1024);
+
+    // interrupted chunk
+    sessions.begin_write(id, Id(1), 1024).unwrap();
+    sessions.end_write(id, None);
+    assert_eq!(sessions.received(id, Id(1)).unwrap(), 1024);
+
+    assert!(matches!(
+        sessions.begin_write(id, Id(1), 2048),
+        Err(Error::ResourceUploadOffsetMismatch { expected: 1024 })
+    ));
+    // resend the last received bytes
+    sessions.begin_write(id, Id(1), 512).unwrap();
+    sessions.end_write(id, Some(1024));
+    assert_eq!(sessions.received(id, Id(1)).unwrap(), 1536);
+
+    // finalizing
+    assert!(matches!(
+        sessions.take(id, Id(2)),
+        Err(Error::PermissionDenied)
+    ));
+    let resource = sessions.take(id, Id(1)).unwrap();
+    assert!(!sessions.contains(id));
+    sessions.insert(resource);
+    assert_eq!(sessions.received(id, Id(1)).unwrap(), 0);
+}
+
+#[test]
+fn shared_resource_ref_count() {
+    let mut resource = Resource::new(Variant::Video { duration: 60 }, Id(1));
+    resource.block(1);
+    resource.block(2);
+    resource.block(2);
+    assert_eq!(resource.ref_count(), 2);
+
+    resource.unblock(1);
+    assert!(resource.is_blocked());
+    resource.unblock(2);
+    assert!(!resource.is_blocked());
+}
+
+#[test]
+fn text_variant() {
+    let text = |title: &str, body: &str| Variant::Text {
+        title: title.to_owned(),
+        body: body.to_owned(),
+        style: Default::default(),
+        duration: 10,
+    };
+    assert!(text("Club Fair", "Join us **now**!").validate().is_ok());
+    assert!(text("", " ").validate().is_err());
+    assert!(text("", &"a".repeat(Variant::MAX_TEXT_BODY_LEN + 1))
+        .validate()
+        .is_err());
+    assert!(!text("Club Fair", "").has_payload());
+
+    let styled = |style: TextStyle| Variant::Text {
+        title: "Club Fair".to_owned(),
+        body: String::new(),
+        style,
+        duration: 10,
+    };
+    let colored = |color: &str| TextStyle {
+        color: Some(color.to_owned()),
+        ..Default::default()
+    };
+    for color in ["#fff", "#ffff", "#1e90ff", "#1e90ff80", "crimson"] {
+        assert!(styled(colored(color)).validate().is_ok(), "{color}");
+    }
+    let long = "a".repeat(33);
+    for color in ["", "#", "#12345", "#ggg", "red;", "url(x)", long.as_str()] {
+        assert!(styled(colored(color)).validate().is_err(), "{color}");
+    }
+    assert!(styled(TextStyle {
+        background: Some("rgb(0, 0, 0)".to_owned()),
+        ..Default::default()
+    })
+    .validate()
+    .is_err());
+    for (size, valid) in [(16, true), (2, false), (4096, false)] {
+        let style = TextStyle {
+            font_size: Some(size),
+            ..Default::default()
+        };
+        assert_eq!(styled(style).validate().is_ok(), valid);
+    }
+}
+
+#[test]
+fn html_bundle() {
+    use std::io::{Cursor, Read, Write};
+
+    use sms4_backend::resource::bundle;
+
+    let zip = |files: &[(&str, &[u8])]| {
+        let mut writer = zip::ZipWriter::new(Cursor::new(vec![]));
+        for (path, content) in files {
+            writer
+                .start_file(*path, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(content).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    };
+
+    let bytes = zip(&[
+        ("index.html", b"<script src=\"js/app.js\"></script>"),
+        ("js/app.js", b"console.log(1)"),
+    ]);
+    let (files, len) = bundle::validate(Cursor::new(&bytes), "index.html").unwrap();
+    assert_eq!(&*files, ["index.html", "js/app.js"]);
+    assert_eq!(len, 47);
+    assert!(matches!(
+        bundle::validate(Cursor::new(&bytes), "main.html"),
+        Err(Error::InvalidHtmlBundle(_))
+    ));
+    let mut unpacked = vec![];
+    bundle::unpack(Cursor::new(&bytes), |path, content| {
+        let mut buf = vec![];
+        content.read_to_end(&mut buf).unwrap();
+        unpacked.push((path, buf));
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(
+        unpacked[1],
+        ("js/app.js".to_owned(), b"console.log(1)".to_vec())
+    );
+
+    let bytes = zip(&[("index.html", b""), ("../escape.js", b"")]);
+    assert!(matches!(
+        bundle::validate(Cursor::new(&bytes), "index.html"),
+        Err(Error::InvalidHtmlBundle(_))
+    ));
+}
+
+/// Builds an MP4 file of a 1920x1080 H.264 video with given duration
+/// as milliseconds, and media data of given length before the movie box.
+fn mp4(duration: u32, mdat: usize) -> Vec<u8> {
+    fn mp4_box(ty: &[u8; 4], content: &[u8]) -> Vec<u8> {
+        let mut bytes = ((content.len() + 8) as u32).to_be_bytes().to_vec();
+        bytes.extend_from_slice(ty);
+        bytes.extend_from_slice(content);
+        bytes
+    }
+
+    let mvhd = [
+        &[0; 12][..],
+        &1000u32.to_be_bytes(),
+        &duration.to_be_bytes(),
+    ]
+    .concat();
+    let tkhd = [
+        &[0; 76][..],
+        &(1920u32 << 16).to_be_bytes(),
+        &(1080u32 << 16).to_be_bytes(),
+    ]
+    .concat();
+    let hdlr = [&[0; 8][..], b"vide", &[0; 12]].concat();
+    let stsd = [&[0; 8][..], &mp4_box(b"avc1", &[0; 78])].concat();
+    let stbl = mp4_box(b"stbl", &mp4_box(b"stsd", &stsd));
+    let mdia = [mp4_box(b"hdlr", &hdlr), mp4_box(b"minf", &stbl)].concat();
+    let trak = [mp4_box(b"tkhd", &tkhd), mp4_box(b"mdia", &mdia)].concat();
+    let moov = [mp4_box(b"mvhd", &mvhd), mp4_box(b"trak", &trak)].concat();
+    let mut bytes = mp4_box(b"ftyp", b"isom");
+    if mdat > 0 {
+        bytes.extend(mp4_box(b"mdat", &vec![0; mdat]));
+    }
+    bytes.extend(mp4_box(b"moov", &moov));
+    bytes
+}
+
+#[test]
+fn video_probe() {
+    use std::io::Cursor;
+
+    use sms4_backend::resource::{
+        sniff::Format,
+        video::{VideoCodec, VideoMeta},
+    };
+
+    fn ebml(id: &[u8], content: &[u8]) -> Vec<u8> {
+        let mut bytes = id.to_vec();
+        bytes.push(0x80 | content.len() as u8);
+        bytes.extend_from_slice(content);
+        bytes
+    }
+
+    let video = VideoMeta::probe(Format::Mp4, Cursor::new(mp4(61500, 0))).unwrap();
+    assert_eq!((video.width, video.height), (1920, 1080));
+    assert_eq!(video.codec, VideoCodec::H264);
+    assert!(video.playable);
+    assert_eq!(video.duration_secs(), Some(62));
+    // media data before the movie box is skipped
+    let video = VideoMeta::probe(Format::Mp4, Cursor::new(mp4(61500, 4096))).unwrap();
+    assert_eq!(video.duration_secs(), Some(62));
+    let video = VideoMeta::probe(Format::Mp4, Cursor::new(mp4(0, 0))).unwrap();
+    assert_eq!(video.duration, None);
+    let truncated = mp4(61500, 0);
+    assert!(
+        VideoMeta::probe(Format::Mp4, Cursor::new(&truncated[..truncated.len() - 1])).is_none()
+    );
+
+    let track = [
+        ebml(&[0x83], &[1]),
+        ebml(&[0x86], b"V_VP9"),
+        ebml(
+            &[0xe0],
+            &[ebml(&[0xb0], &[0x05, 0x00]), ebml(&[0xba], &[0x02, 0xd0])].concat(),
+        ),
+    ]
+    .concat();
+    let info = [
+        ebml(&[0x2a, 0xd7, 0xb1], &[0x0f, 0x42, 0x40]),
+        ebml(&[0x44, 0x89], &30000f64.to_be_bytes()),
+    ]
+    .concat();
+    let segment = [
+        // void element, which is skipped
+        ebml(&[0xec], &[0; 16]),
+        ebml(&[0x15, 0x49, 0xa9, 0x66], &info),
+        ebml(&[0x16, 0x54, 0xae, 0x6b], &ebml(&[0xae], &track)),
+    ]
+    .concat();
+    // segment of unknown size
+    let webm = [
+        ebml(&[0x1a, 0x45, 0xdf, 0xa3], &ebml(&[0x42, 0x82], b"webm")),
+        vec![0x18, 0x53, 0x80, 0x67, 0xff],
+        segment,
+    ]
+    .concat();
+    let video = VideoMeta::probe(Format::WebM, Cursor::new(&webm)).unwrap();
+    assert_eq!((video.width, video.height), (1280, 720));
+    assert_eq!(video.codec, VideoCodec::Vp9);
+    assert_eq!(video.duration_secs(), Some(30));
+    assert!(VideoMeta::probe(Format::WebM, Cursor::new(&webm[..20])).is_none());
+
+    let mut variant = Variant::Video { duration: 60 };
+    variant.fit_video_duration(62).unwrap();
+    assert_eq!(variant, Variant::Video { duration: 62 });
+    assert!(matches!(
+        variant.fit_video_duration(30),
+        Err(Error::VideoDurationMismatch { .. })
+    ));
+}
+
+#[test]
+fn payload_limit() {
+    use sms4_backend::config::PayloadLimit;
+
+    let limit: PayloadLimit = serde_json::from_str(r#"{ "video": 524288000 }"#).unwrap();
+    assert_eq!(limit.of(&Variant::Video { duration: 60 }), 524288000);
+    assert_eq!(
+        limit.of(&Variant::Image { duration: 10 }),
+        PayloadLimit::default().default
+    );
+}
+
+#[test]
+fn format_detect() {
+    use sms4_backend::resource::sniff::Format;
+
+    assert_eq!(
+        Format::detect(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"),
+        Some(Format::Png)
+    );
+    assert_eq!(
+        Format::detect(&[0xff, 0xd8, 0xff, 0xe0]),
+        Some(Format::Jpeg)
+    );
+    assert_eq!(Format::detect(b"RIFF\0\0\0\0WEBPVP8X"), Some(Format::WebP));
+    assert_eq!(Format::detect(b"GIF89a\x01\0"), Some(Format::Gif));
+    assert_eq!(Format::detect(b"%PDF-1.7"), Some(Format::Pdf));
+    assert_eq!(Format::detect(b"PK\x03\x04"), Some(Format::Zip));
+    assert_eq!(
+        Format::detect(b"\0\0\0\x18ftypisom\0\0\0\0"),
+        Some(Format::Mp4)
+    );
+    assert_eq!(
+        Format::detect(b"\x1a\x45\xdf\xa3\x9f\x42\x82\x84webm"),
+        Some(Format::WebM)
+    );
+
+    // HEIF images share the container of MP4
+    assert_eq!(Format::detect(b"\0\0\0\x18ftypheic\0\0\0\0"), None);
+    assert_eq!(
+        Format::detect(b"\x1a\x45\xdf\xa3\x9f\x42\x82\x88matroska"),
+        None
+    );
+    // truncated
+    assert_eq!(Format::detect(b"\x89PNG"), None);
+    assert_eq!(Format::detect(b"RIFF\0\0\0\0WEB"), None);
+    assert_eq!(Format::detect(b""), None);
+}
+
+#[test]
+fn image_meta() {
+    use std::io::Cursor;
+
+    use image::{DynamicImage, ImageOutputFormat};
+    use sms4_backend::resource::{meta::ImageMeta, sniff::Format};
+
+    fn encode(image: DynamicImage, format: ImageOutputFormat) -> Vec<u8> {
+        let mut buf = vec![];
+        image.write_to(&mut Cursor::new(&mut buf), format).unwrap();
+        buf
+    }
+    /// Inserts an EXIF segment with given TIFF structure after SOI.
+    fn with_exif(jpeg: &[u8], tiff: &[u8]) -> Vec<u8> {
+        let len = (tiff.len() + 8) as u16;
+        [
+            &jpeg[..2],
+            &[0xff, 0xe1],
+            &len.to_be_bytes(),
+            b"Exif\0\0",
+            tiff,
+            &jpeg[2..],
+        ]
+        .concat()
+    }
+    /// A TIFF structure with the orientation as the only IFD entry.
+    fn tiff(big_endian: bool, orientation: u16) -> Vec<u8> {
+        let u16_of = |v: u16| {
+            if big_endian {
+                v.to_be_bytes()
+            } else {
+                v.to_le_bytes()
+            }
+        };
+        let u32_of = |v: u32| {
+            if big_endian {
+                v.to_be_bytes()
+            } else {
+                v.to_le_bytes()
+            }
+        };
+        [
+            &if big_endian { *b"MM" } else { *b"II" }[..],
+            &u16_of(42),
+            &u32_of(8),
+            &u16_of(1),
+            &u16_of(0x0112),
+            &u16_of(3),
+            &u32_of(1),
+            &u16_of(orientation),
+            &[0, 0],
+            &u32_of(0),
+        ]
+        .concat()
+    }
+
+    let meta = |width, height, orientation| ImageMeta {
+        width,
+        height,
+        orientation,
+    };
+
+    let png = encode(DynamicImage::new_rgb8(48, 32), ImageOutputFormat::Png);
+    assert_eq!(ImageMeta::extract(Format::Png, &png), Some(meta(48, 32, 1)));
+    assert_eq!(ImageMeta::extract(Format::Png, &png[..16]), None);
+
+    let gif = encode(DynamicImage::new_rgba8(20, 10), ImageOutputFormat::Gif);
+    assert_eq!(ImageMeta::extract(Format::Gif, &gif), Some(meta(20, 10, 1)));
+    assert_eq!(ImageMeta::extract(Format::Gif, &gif[..7]), None);
+
+    let jpeg = encode(DynamicImage::new_rgb8(64, 40), ImageOutputFormat::Jpeg(80));
+    assert_eq!(
+        ImageMeta::extract(Format::Jpeg, &jpeg),
+        Some(meta(64, 40, 1))
+    );
+    assert_eq!(
+        ImageMeta::extract(Format::Jpeg, &with_exif(&jpeg, &tiff(false, 6))),
+        Some(meta(64, 40, 6))
+    );
+    assert_eq!(
+        ImageMeta::extract(Format::Jpeg, &with_exif(&jpeg, &tiff(true, 8))),
+        Some(meta(64, 40, 8))
+    );
+    // out of range orientation
+    assert_eq!(
+        ImageMeta::extract(Format::Jpeg, &with_exif(&jpeg, &tiff(false, 9))),
+        Some(meta(64, 40, 1))
+    );
+    // truncated EXIF
+    assert_eq!(
+        ImageMeta::extract(Format::Jpeg, &with_exif(&jpeg, &tiff(true, 6)[..10])),
+        Some(meta(64, 40, 1))
+    );
+    // truncated before the frame header
+    assert_eq!(ImageMeta::extract(Format::Jpeg, &jpeg[..20]), None);
+
+    assert_eq!(ImageMeta::extract(Format::Pdf, &png), None);
+}
+
+/// Builds a PDF document with given number of empty pages.
+fn pdf(pages: usize) -> Vec<u8> {
+    use lopdf::{dictionary, Document, Object};
+
+    let mut doc = Document::with_version("1.5");
+    let pages_id = doc.new_object_id();
+    let kids: Vec<Object> = (0..pages)
+        .map(|_| {
+            doc.add_object(dictionary! {
+                "Type" => "Page",
+                "Parent" => pages_id,
+            })
+            .into()
+        })
+        .collect();
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => kids,
+            "Count" => pages as i64,
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+    let mut bytes = vec![];
+    doc.save_to(&mut bytes).unwrap();
+    bytes
+}
+
+#[tokio::test]
+async fn pdf_pages() {
+    let (state, route) = router();
+    let mut account: Account = acc_exp!(DCK, UploadResource);
+    let (token, _) = account.login("shanlilinghuo").unwrap();
+    let id = account.id();
+    state.worlds.account.insert(account).await.unwrap();
+
+    // undeclared pages are filled in
+    let res = upload(
+        &route,
+        id,
+        &token,
+        json!({ "type": "Pdf", "pages": 0, "durations": [] }),
+        pdf(3),
+    )
+    .await;
+    assert!(res.status().is_success());
+    let res: serde_json::Value = p_json!(res);
+    let resource_id: u64 = res["id"].as_str().unwrap().parse().unwrap();
+    {
+        let select = sd!(state.worlds.resource, resource_id);
+        let lazy = gd!(select, resource_id).unwrap();
+        let Variant::Pdf { pages, durations } = lazy.get().await.unwrap().variant().clone() else {
+            unreachable!()
+        };
+        assert_eq!(pages, 3);
+        assert_eq!(durations.len(), 3);
+    }
+
+    let res = upload(
+        &route,
+        id,
+        &token,
+        json!({ "type": "Pdf", "pages": 2, "durations": [10, 10] }),
+        pdf(3),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    // broken documents with a PDF header
+    let res = upload(
+        &route,
+        id,
+        &token,
+        json!({ "type": "Pdf", "pages": 0, "durations": [] }),
+        b"%PDF-1.7\nnot really a document".to_vec(),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+}
+
+#[test]
+fn thumbnails() {
+    use std::io::Cursor;
+
+    use image::{DynamicImage, GenericImageView, ImageOutputFormat};
+    use sms4_backend::resource::{sniff::Format, thumbnail};
+
+    assert_eq!(thumbnail::fit_size(0), 128);
+    assert_eq!(thumbnail::fit_size(128), 128);
+    assert_eq!(thumbnail::fit_size(129), 256);
+    assert_eq!(thumbnail::fit_size(4096), 512);
+
+    let mut png = vec![];
+    DynamicImage::new_rgb8(400, 200)
+        .write_to(&mut Cursor::new(&mut png), ImageOutputFormat::Png)
+        .unwrap();
+    let dimensions = |orientation| {
+        thumbnail::generate(Format::Png, &png, orientation)
+            .unwrap()
+            .into_iter()
+            .map(|(size, buf)| (size, image::load_from_memory(&buf).unwrap().dimensions()))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        dimensions(1),
+        [(128, (128, 64)), (256, (256, 128)), (512, (512, 256))]
+    );
+    // rotated by 90 degrees
+    assert_eq!(dimensions(6)[0], (128, (64, 128)));
+
+    assert!(thumbnail::generate(Format::Png, &png[..32], 1).is_none());
+    assert!(thumbnail::generate(Format::Mp4, &png, 1).is_none());
+}
+
+#[test]
+fn payload_range() {
+    use crate::handle::resource::parse_range;
+
+    assert_eq!(parse_range("bytes=0-99", 1000), Some(Ok(0..=99)));
+    // suffix
+    assert_eq!(parse_range("bytes=-100", 1000), Some(Ok(900..=999)));
+    assert_eq!(parse_range("bytes=-2000", 1000), Some(Ok(0..=999)));
+    assert_eq!(parse_range("bytes=-0", 1000), Some(Err(())));
+    // open-ended
+    assert_eq!(parse_range("bytes=100-", 1000), Some(Ok(100..=999)));
+    assert_eq!(parse_range("bytes=100-5000", 1000), Some(Ok(100..=999)));
+    // past the end
+    assert_eq!(parse_range("bytes=1000-", 1000), Some(Err(())));
+    assert_eq!(parse_range("bytes=2000-3000", 1000), Some(Err(())));
+    assert_eq!(parse_range("bytes=-100", 0), Some(Err(())));
+    // ignored
+    assert_eq!(parse_range("bytes=500-100", 1000), None);
+    assert_eq!(parse_range("bytes=0-1,5-6", 1000), None);
+    assert_eq!(parse_range("items=0-1", 1000), None);
+    assert_eq!(parse_range("bytes=a-b", 1000), None);
+}
+
+/// Uploads the payload as an image with a new session.
+async fn upload_image(
+    route: &Router,
+    id: u64,
+    token: &str,
+    payload: Vec<u8>,
+) -> axum::response::Response {
+    upload(
+        route,
+        id,
+        token,
+        json!({ "type": "Image", "duration": 15 }),
+        payload,
+    )
+    .await
+}
+
+/// Uploads the payload as the given variant with a new session.
+///
+/// Buffers of rejected uploads are removed, as the buffer
+/// directory is shared by tests.
+async fn upload(
+    route: &Router,
+    id: u64,
+    token: &str,
+    variant: serde_json::Value,
+    payload: Vec<u8>,
+) -> axum::response::Response {
+    let res = req!(route, PUT => NEW_UPLOAD_SESSION,
+        Auth { account: id, token: token.to_owned() },
+        json!({ "variant": variant }) => json
+    );
+    assert!(res.status().is_success());
+    let res: serde_json::Value = p_json!(res);
+    let session = res["id"].as_str().unwrap();
+    let res = req!(route, PUT => format!("/resource/upload/{session}"),
+        Auth { account: id, token: token.to_owned() },
+        payload => bytes
+    );
+    if !res.status().is_success() {
+        let _ = tokio::fs::remove_file(format!(".test/resources/buf_{session}")).await;
+    }
+    res
+}
+
+/// Gets used bytes of the account and its `SubIT` department.
+async fn used(route: &Router, id: u64, token: &str) -> (u64, u64) {
+    let res = req!(route, GET => GET_RESOURCE_USAGE,
+        Auth { account: id, token: token.to_owned() }
+    );
+    assert!(res.status().is_success());
+    let res: serde_json::Value = p_json!(res);
+    (
+        res["account"]["used"].as_u64().unwrap(),
+        res["departments"]["SubIT"]["used"].as_u64().unwrap(),
+    )
+}
+
+#[tokio::test]
+async fn upload_quota() {
+    use sms4_backend::resource::usage::UsageOwner;
+
+    let (state, route) = router_with(|config| {
+        config.quota.account = Some(100);
+        config.quota.departments.insert("SubIT".to_owned(), 1000);
+    });
+    let mut account: Account = acc_exp!(DCK, UploadResource);
+    let (token, _) = account.login("shanlilinghuo").unwrap();
+    let id = account.id();
+    let owners = UsageOwner::of(&account);
+    state.worlds.account.insert(account).await.unwrap();
+
+    // A PNG signature followed by garbage is accepted without image metadata.
+    let payload = |fill: u8| {
+        let mut payload = b"\x89PNG\r\n\x1a\n".to_vec();
+        payload.resize(60, fill);
+        payload
+    };
+    let res = upload_image(&route, id, &token, payload(0)).await;
+    assert!(res.status().is_success());
+    let res: serde_json::Value = p_json!(res);
+    let resource_id: u64 = res["id"].as_str().unwrap().parse().unwrap();
+    assert_eq!(used(&route, id, &token).await, (60, 60));
+    {
+        let select = sd!(state.worlds.resource, resource_id);
+        let lazy = gd!(select, resource_id).unwrap();
+        assert_eq!(lazy.get().await.unwrap().charged(), owners);
+    }
+
+    let res = upload_image(&route, id, &token, payload(1)).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    assert_eq!(used(&route, id, &token).await, (60, 60));
+
+    let res = req!(route, DELETE => format!("/resource/delete/{resource_id}"),
+        Auth { account: id, token: token.clone() }
+    );
+    assert!(res.status().is_success());
+    assert_eq!(used(&route, id, &token).await, (0, 0));
+
+    let res = upload_image(&route, id, &token, payload(1)).await;
+    assert!(res.status().is_success());
+    assert_eq!(used(&route, id, &token).await, (60, 60));
+}
+
+#[tokio::test]
+async fn list_and_remove_mine() {
+    let (state, route) = router_with(|_| {});
+    let mut account: Account = acc_exp!(DCK, UploadResource);
+    let (token, _) = account.login("shanlilinghuo").unwrap();
+    let id = account.id();
+    state.worlds.account.insert(account).await.unwrap();
+
+    let text = |title: &str| Variant::Text {
+        title: title.to_owned(),
+        body: String::new(),
+        style: Default::default(),
+        duration: 15,
+    };
+    let unused = Resource::new(text("Unused"), Id(id));
+    let mut used = Resource::new(text("Used"), Id(id));
+    used.block(1);
+    let others = Resource::new(text("Others"), Id(id.wrapping_add(1)));
+    let (unused_id, used_id, others_id) = (unused.id(), used.id(), others.id());
+    for resource in [unused, used, others] {
+        state.worlds.resource.insert(resource).await.unwrap();
+    }
+
+    let res = req!(route, GET => LIST_MY_RESOURCES,
+        Auth { account: id, token: token.clone() }
+    );
+    assert!(res.status().is_success());
+    let res: serde_json::Value = p_json!(res);
+    let mut resources: Vec<(String, bool)> = res["resources"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| {
+            (
+                r["id"].as_str().unwrap().to_owned(),
+                r["used"].as_bool().unwrap(),
+            )
+        })
+        .collect();
+    resources.sort();
+    let mut expected = vec![(unused_id.to_string(), false), (used_id.to_string(), true)];
+    expected.sort();
+    assert_eq!(resources, expected);
+
+    let res = req!(route, DELETE => format!("/resource/delete/{used_id}"),
+        Auth { account: id, token: token.clone() }
+    );
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+    let res = req!(route, DELETE => format!("/resource/delete/{others_id}"),
+        Auth { account: id, token: token.clone() }
+    );
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    let res = req!(route, DELETE => format!("/resource/delete/{unused_id}"),
+        Auth { account: id, token: token.clone() }
+    );
+    assert!(res.status().is_success());
+
+    let select = sd!(state.worlds.resource, unused_id);
+    assert!(gd!(select, unused_id).is_none());
+    let select = sd!(state.worlds.resource, used_id);
+    assert!(gd!(select, used_id).is_some());
+    let select = sd!(state.worlds.resource, others_id);
+    assert!(gd!(select, others_id).is_some());
+}
+
+#[tokio::test]
+async fn text_quota() {
+    let (state, route) = router_with(|config| config.quota.account = Some(40));
+    let mut account: Account = acc_exp!(DCK, UploadResource);
+    let (token, _) = account.login("shanlilinghuo").unwrap();
+    let id = account.id();
+    state.worlds.account.insert(account).await.unwrap();
+
+    let text = json!({
+        "title": "Club Fair",
+        "body": "Join us **now**!",
+        "duration": 15,
+    });
+    let res = req!(route, PUT => NEW_TEXT_RESOURCE,
+        Auth { account: id, token: token.clone() },
+        text => json
+    );
+    assert!(res.status().is_success());
+    let res: serde_json::Value = p_json!(res);
+    let resource_id: u64 = res["id"].as_str().unwrap().parse().unwrap();
+    assert_eq!(used(&route, id, &token).await, (25, 25));
+
+    let res = req!(route, PUT => NEW_TEXT_RESOURCE,
+        Auth { account: id, token: token.clone() },
+        text => json
+    );
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    assert_eq!(used(&route, id, &token).await, (25, 25));
+
+    let res = req!(route, DELETE => format!("/resource/delete/{resource_id}"),
+        Auth { account: id, token: token.clone() }
+    );
+    assert!(res.status().is_success());
+    assert_eq!(used(&route, id, &token).await, (0, 0));
+}
+
+#[tokio::test]
+async fn serve_approved_bundle() {
+    use std::io::{Cursor, Write};
+
+    let (state, route) = router_with(|_| {});
+    let mut account: Account = acc_exp!(DCK, UploadResource, Post);
+    let (token, _) = account.login("shanlilinghuo").unwrap();
+    let id = account.id();
+    state.worlds.account.insert(account).await.unwrap();
+
+    let mut writer = zip::ZipWriter::new(Cursor::new(vec![]));
+    writer
+        .start_file("index.html", zip::write::SimpleFileOptions::default())
+        .unwrap();
+    writer.write_all(b"<p>Club Fair</p>").unwrap();
+    let bytes = writer.finish().unwrap().into_inner();
+    let len = bytes.len() as u64;
+
+    let res = req!(route, PUT => NEW_UPLOAD_SESSION,
+        Auth { account: id, token: token.clone() },
+        json!({ "variant": { "type": "Html", "entry": "index.html", "duration": 15 } }) => json
+    );
+    assert!(res.status().is_success());
+    let res: serde_json::Value = p_json!(res);
+    let session = res["id"].as_str().unwrap().to_owned();
+    let res = req!(route, PUT => format!("/resource/upload/{session}"),
+        Auth { account: id, token: token.clone() },
+        bytes => bytes
+    );
+    assert!(res.status().is_success());
+    let res: serde_json::Value = p_json!(res);
+    let resource_id: u64 = res["id"].as_str().unwrap().parse().unwrap();
+    // both the archive and unpacked files are charged
+    assert_eq!(used(&route, id, &token).await.0, len + 16);
+
+    let today = OffsetDateTime::now_utc().date();
+    let res = req!(route, PUT => NEW_POST,
+        Auth { account: id, token },
+        json!({
+            "title": "Club",
+            "notes": "",
+            "time": { "start": today, "end": today + Duration::DAY },
+            "resources": [resource_id],
+            "grouped": false,
+            "priority": "Normal",
+        }) => json
+    );
+    assert!(res.status().is_success());
+    let res: serde_json::Value = p_json!(res);
+    let post_id: u64 = res["id"].as_str().unwrap().parse().unwrap();
+
+    let url = format!("/sandbox/html/{resource_id}/index.html");
+    let res = req!(route, GET => &url, Vec::<u8>::new() => bytes);
+    assert!(!res.status().is_success());
+
+    {
+        let select = sd!(state.worlds.post, post_id);
+        let mut lazy = gd!(select, post_id).unwrap();
+        lazy.get_mut()
+            .await
+            .unwrap()
+            .pust_state(State::new(Status::Approved, id, String::new()))
+            .unwrap();
+        lazy.close().await.unwrap();
+    }
+    let res = req!(route, GET => &url, Vec::<u8>::new() => bytes);
+    assert!(res.status().is_success());
+    assert!(res
+        .headers()
+        .contains_key(axum::http::header::CONTENT_SECURITY_POLICY));
+    let body = http_body_util::BodyExt::collect(res.into_body())
+        .await
+        .unwrap()
+        .to_bytes();
+    assert_eq!(&*body, b"<p>Club Fair</p>");
+    let res = req!(route, GET => format!("/sandbox/html/{resource_id}/missing.html"), Vec::<u8>::new() => bytes);
+    assert!(!res.status().is_success());
+}
+
+#[tokio::test]
+async fn unknown_video_duration() {
+    let (state, route) = router_with(|_| {});
+    let mut account: Account = acc_exp!(DCK, UploadResource);
+    let (token, _) = account.login("shanlilinghuo").unwrap();
+    let id = account.id();
+    state.worlds.account.insert(account).await.unwrap();
+
+    for (duration, status) in [(0, StatusCode::BAD_REQUEST), (60, StatusCode::OK)] {
+        let res = req!(route, PUT => NEW_UPLOAD_SESSION,
+            Auth { account: id, token: token.clone() },
+            json!({ "variant": { "type": "Video", "duration": duration } }) => json
+        );
+        assert!(res.status().is_success());
+        let res: serde_json::Value = p_json!(res);
+        let session = res["id"].as_str().unwrap().to_owned();
+        let res = req!(route, PUT => format!("/resource/upload/{session}"),
+            Auth { account: id, token: token.clone() },
+            mp4(0, 0) => bytes
+        );
+        assert_eq!(res.status(), status);
+    }
+}
+
+#[tokio::test]
+async fn scrub_damaged_files() {
+    let (state, route) = router();
+    let mut viewer: Account = acc_exp!(MYG, GetPubPost);
+    let (token, _) = viewer.login("123456").unwrap();
+    let viewer_id = viewer.id();
+    state.worlds.account.insert(viewer).await.unwrap();
+
+    let payload = b"not really a video";
+    let mut sessions = UploadSessions::new();
+    let resource = Resource::new(Variant::Video { duration: 60 }, Id(1));
+    let session = resource.id();
+    sessions.insert(resource);
+    let mut hasher = highway::PortableHash::default();
+    highway::HighwayHash::append(&mut hasher, payload);
+    let mut resource = sessions.accept(Id(session), hasher, Id(1)).unwrap();
+
+    let today = OffsetDateTime::now_utc().date();
+    let mut post = Post::new(
+        "Club".to_owned(),
+        String::new(),
+        today..=(today + Duration::DAY),
+        Box::new([Id(resource.id())]),
+        1,
+        false,
+        Priority::Normal,
+        Post::MAX_DUR,
+    )
+    .unwrap();
+    post.pust_state(State::system(Status::Approved, String::new()))
+        .unwrap();
+    let post_id = post.id();
+    resource.block(post_id);
+    let file_name = resource.file_name();
+    let resource_id = resource.id();
+    state.worlds.post.insert(post).await.unwrap();
+    state.worlds.resource.insert(resource).await.unwrap();
+
+    state
+        .resource_store
+        .put_bytes(&file_name, payload.to_vec().into())
+        .await
+        .unwrap();
+    let report = crate::job::scrub(&state, false).await.unwrap();
+    assert_eq!(report.checked, 1);
+    assert!(report.corrupted.is_empty() && report.missing.is_empty());
+
+    state
+        .resource_store
+        .put_bytes(&file_name, b"not really a video!".to_vec().into())
+        .await
+        .unwrap();
+    let report = crate::job::scrub(&state, false).await.unwrap();
+    assert_eq!(report.corrupted, [file_name.clone()]);
+    assert_eq!(report.posts, [Id(post_id)]);
+
+    // damaged resources are visible to viewers of the post
+    let res = req!(route, GET => format!("/post/get/{post_id}"),
+        Auth { account: viewer_id, token }
+    );
+    assert!(res.status().is_success());
+    let res: serde_json::Value = p_json!(res);
+    assert_eq!(res["type"], "Simple");
+    assert_eq!(res["damaged_resources"], json!([resource_id.to_string()]));
+
+    state.resource_store.delete(&file_name).await.unwrap();
+    let report = crate::job::scrub(&state, false).await.unwrap();
+    assert_eq!(report.missing, [file_name.clone()]);
+    {
+        let select = sd!(state.worlds.post, post_id);
+        let lazy = gd!(select, post_id).unwrap();
+        assert_eq!(
+            lazy.get().await.unwrap().damaged_resources(),
+            [Id(resource_id)]
+        );
+    }
+
+    // marks are cleared once the file is restored
+    state
+        .resource_store
+        .put_bytes(&file_name, payload.to_vec().into())
+        .await
+        .unwrap();
+    let report = crate::job::scrub(&state, false).await.unwrap();
+    assert!(report.missing.is_empty() && report.corrupted.is_empty());
+    assert_eq!(report.cleared, [Id(post_id)]);
+    let select = sd!(state.worlds.post, post_id);
+    let lazy = gd!(select, post_id).unwrap();
+    assert!(lazy.get().await.unwrap().damaged_resources().is_empty());
+    let report = crate::job::scrub(&state, false).await.unwrap();
+    assert!(report.cleared.is_empty());
+}
+
+#[tokio::test]
+async fn collect_garbage() {
+    let (state, _) = router_with(|config| config.resource_path = PathBuf::from(".test/gc"));
+    tokio::fs::create_dir_all(&state.config.resource_path)
+        .await
+        .unwrap();
+
+    let payload = b"not really a video";
+    let mut sessions = UploadSessions::new();
+    let resource = Resource::new(Variant::Video { duration: 60 }, Id(1));
+    let session = resource.id();
+    sessions.insert(resource);
+    let mut hasher = highway::PortableHash::default();
+    highway::HighwayHash::append(&mut hasher, payload);
+    let resource = sessions.accept(Id(session), hasher, Id(1)).unwrap();
+    let referred = resource.file_name();
+    let hash = resource.hash();
+    state.worlds.resource.insert(resource).await.unwrap();
+
+    let orphan = Resource::file_name_of(hash.wrapping_add(1));
+    let orphan_thumbnail = Resource::thumbnail_name_of(hash.wrapping_add(1), 64);
+    for name in [&referred, &orphan, &orphan_thumbnail] {
+        state
+            .resource_store
+            .put_bytes(name, payload.to_vec().into())
+            .await
+            .unwrap();
+    }
+
+    // buffer of an alive session, and one whose session has expired
+    let alive = Resource::new(Variant::Video { duration: 60 }, Id(1));
+    let alive_buf = alive.buf_name();
+    state.resource_sessions.lock().await.insert(alive);
+    let stale_buf = Resource::new(Variant::Video { duration: 60 }, Id(1)).buf_name();
+    for name in [&alive_buf, &stale_buf] {
+        tokio::fs::write(state.config.resource_path.join(name), payload)
+            .await
+            .unwrap();
+    }
+
+    // files within the grace period are kept
+    let report = crate::job::collect_garbage(&state, false).await.unwrap();
+    assert!(report.orphans.is_empty() && report.stale_buffers.is_empty());
+    assert_eq!(report.bytes, 0);
+
+    let later = SystemTime::now() + std::time::Duration::from_secs(2 * 60 * 60);
+    let mut orphans = [orphan.clone(), orphan_thumbnail.clone()];
+    orphans.sort();
+    for dry_run in [true, false] {
+        let mut report = crate::job::collect_garbage_at(&state, dry_run, later)
+            .await
+            .unwrap();
+        report.orphans.sort();
+        assert_eq!(report.orphans, orphans);
+        assert_eq!(report.stale_buffers, [stale_buf.clone()]);
+        assert_eq!(report.bytes, payload.len() as u64 * 3);
+
+        // dry runs remove nothing
+        assert_eq!(state.resource_store.head(&orphan).await.is_ok(), dry_run);
+        assert_eq!(
+            state.config.resource_path.join(&stale_buf).exists(),
+            dry_run
+        );
+    }
+    assert!(state.resource_store.head(&referred).await.is_ok());
+    assert!(state.config.resource_path.join(&alive_buf).exists());
+    let report = crate::job::collect_garbage_at(&state, false, later)
+        .await
+        .unwrap();
+    assert!(report.orphans.is_empty() && report.stale_buffers.is_empty());
+
+    tokio::fs::remove_file(state.config.resource_path.join(alive_buf))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn upload_payload_limit() {
+    let (state, route) = router_with(|config| config.payload_limit.image = Some(100));
+    let mut account: Account = acc_exp!(DCK, UploadResource);
+    let (token, _) = account.login("shanlilinghuo").unwrap();
+    let id = account.id();
+    state.worlds.account.insert(account).await.unwrap();
+    let mut another: Account = acc_exp!(MYG, UploadResource);
+    let (another_token, _) = another.login("123456").unwrap();
+    let another_id = another.id();
+    state.worlds.account.insert(another).await.unwrap();
+
+    let res = req!(route, PUT => NEW_UPLOAD_SESSION,
+        Auth { account: id, token: token.clone() },
+        json!({ "variant": { "type": "Image", "duration": 15 } }) => json
+    );
+    assert!(res.status().is_success());
+    let res: serde_json::Value = p_json!(res);
+    let session = res["id"].as_str().unwrap().to_owned();
+    let declared = |auth: Auth, len: u64| {
+        let mut b = Some(
+            axum::http::Request::builder()
+                .uri(format!("/resource/upload-chunk/{session}?offset=0"))
+                .method(axum::http::Method::PATCH)
+                .header(axum::http::header::CONTENT_LENGTH, len),
+        );
+        auth.append_to_req_builder(&mut b);
+        b.unwrap()
+            .body(axum::body::Body::from(b"\x89PNG\r\n\x1a\n".to_vec()))
+            .unwrap()
+    };
+    let received = || async {
+        let res = req!(route, GET => format!("/resource/upload-status/{session}"),
+            Auth { account: id, token: token.clone() }
+        );
+        assert!(res.status().is_success());
+        let res: serde_json::Value = p_json!(res);
+        res["received"].as_u64().unwrap()
+    };
+
+    // sessions of others are rejected before their limits are revealed
+    let req = declared(
+        Auth {
+            account: another_id,
+            token: another_token,
+        },
+        200,
+    );
+    let res = tower::ServiceExt::oneshot(route.clone(), req)
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    // declared length exceeding the limit is rejected before being read
+    let req = declared(
+        Auth {
+            account: id,
+            token: token.clone(),
+        },
+        200,
+    );
+    let res = tower::ServiceExt::oneshot(route.clone(), req)
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    assert_eq!(received().await, 0);
+
+    // undeclared length exceeding the limit is aborted while streaming
+    let mut payload = b"\x89PNG\r\n\x1a\n".to_vec();
+    payload.resize(200, 0);
+    let res = req!(route, PATCH => format!("/resource/upload-chunk/{session}?offset=0"),
+        Auth { account: id, token: token.clone() },
+        payload => bytes
+    );
+    assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    assert_eq!(received().await, 0);
+
+    // the session is still writable after the abort
+    let mut payload = b"\x89PNG\r\n\x1a\n".to_vec();
+    payload.resize(100, 0);
+    let res = req!(route, PATCH => format!("/resource/upload-chunk/{session}?offset=0"),
+        Auth { account: id, token: token.clone() },
+        payload => bytes
+    );
+    assert!(res.status().is_success());
+    assert_eq!(received().await, 100);
+    let _ = tokio::fs::remove_file(format!(".test/resources/buf_{session}")).await;
+}
+
+#[tokio::test]
+async fn resource_posts() {
+    use std::collections::HashSet;
+
+    use sms4_backend::account::{Permission, Tag};
+
+    let (state, route) = router();
+    let mut account: Account = acc_exp!(DCK, Post);
+    let (token, _) = account.login("shanlilinghuo").unwrap();
+    let id = account.id();
+    state.worlds.account.insert(account).await.unwrap();
+    let mut another: Account = acc_exp!(MYG, GetPubPost);
+    let (another_token, _) = another.login("123456").unwrap();
+    let another_id = another.id();
+    state.worlds.account.insert(another).await.unwrap();
+
+    let first = Resource::new(Variant::Video { duration: 60 }, Id(id));
+    let second = Resource::new(Variant::Video { duration: 60 }, Id(id));
+    let (first_id, second_id) = (first.id(), second.id());
+    state.worlds.resource.insert(first).await.unwrap();
+    state.worlds.resource.insert(second).await.unwrap();
+
+    let today = OffsetDateTime::now_utc().date();
+    let mut posts = vec![];
+    for _ in 0..2 {
+        let res = req!(route, PUT => NEW_POST,
+            Auth { account: id, token: token.clone() },
+            json!({
+                "title": "Club",
+                "notes": "",
+                "time": { "start": today, "end": today + Duration::DAY },
+                "resources": [first_id],
+                "grouped": false,
+                "priority": "Normal",
+            }) => json
+        );
+        assert!(res.status().is_success());
+        let res: serde_json::Value = p_json!(res);
+        posts.push(res["id"].as_str().unwrap().to_owned());
+    }
+    let ids = |res: serde_json::Value| {
+        res["posts"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|id| id.as_str().unwrap().to_owned())
+            .collect::<HashSet<_>>()
+    };
+    let get_posts = |resource: u64, auth: Auth| {
+        let route = route.clone();
+        async move { req!(route, GET => format!("/resource/posts/{resource}"), auth) }
+    };
+    let filter = |resource: u64| {
+        let route = route.clone();
+        let token = token.clone();
+        async move {
+            let res = req!(route, GET => format!("/post/filter?resource={resource}"),
+                Auth { account: id, token }
+            );
+            assert!(res.status().is_success());
+            let res: serde_json::Value = p_json!(res);
+            res
+        }
+    };
+
+    let res = get_posts(
+        first_id,
+        Auth {
+            account: id,
+            token: token.clone(),
+        },
+    )
+    .await;
+    assert!(res.status().is_success());
+    assert_eq!(ids(p_json!(res)), posts.iter().cloned().collect());
+
+    // others could only look up with `ReviewPost`
+    let res = get_posts(
+        first_id,
+        Auth {
+            account: another_id,
+            token: another_token.clone(),
+        },
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    {
+        let select = sd!(state.worlds.account, another_id);
+        let mut lazy = gd!(select, another_id).unwrap();
+        lazy.get_mut()
+            .await
+            .unwrap()
+            .tags_mut()
+            .insert(Tag::Permission(Permission::ReviewPost));
+        lazy.close().await.unwrap();
+    }
+    let res = get_posts(
+        first_id,
+        Auth {
+            account: another_id,
+            token: another_token,
+        },
+    )
+    .await;
+    assert!(res.status().is_success());
+    assert_eq!(ids(p_json!(res)), posts.iter().cloned().collect());
+
+    let res = req!(route, PATCH => format!("/post/modify/{}", posts[0]),
+        Auth { account: id, token: token.clone() },
+        json!({ "resources": [second_id] }) => json
+    );
+    assert!(res.status().is_success());
+    let res = get_posts(
+        first_id,
+        Auth {
+            account: id,
+            token: token.clone(),
+        },
+    )
+    .await;
+    assert_eq!(ids(p_json!(res)), HashSet::from([posts[1].clone()]));
+    let res = get_posts(
+        second_id,
+        Auth {
+            account: id,
+            token: token.clone(),
+        },
+    )
+    .await;
+    assert_eq!(ids(p_json!(res)), HashSet::from([posts[0].clone()]));
+    assert_eq!(
+        ids(filter(first_id).await),
+        HashSet::from([posts[1].clone()])
+    );
+    assert_eq!(
+        ids(filter(second_id).await),
+        HashSet::from([posts[0].clone()])
+    );
+
+    // resources without posts are removed
+    let res = req!(route, DELETE => format!("/post/delete/{}", posts[1]),
+        Auth { account: id, token: token.clone() }
+    );
+    assert!(res.status().is_success());
+    let res = get_posts(
+        first_id,
+        Auth {
+            account: id,
+            token: token.clone(),
+        },
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    assert!(ids(filter(first_id).await).is_empty());
+    assert_eq!(
+        ids(filter(second_id).await),
+        HashSet::from([posts[0].clone()])
+    );
+}
@@ -0,0 +1,115 @@
+use axum::http::StatusCode;
+use sms4_backend::{
+    account::Account,
+    notification::Notification,
+    post::{Post, Priority, State, Status},
+    resource::{Resource, Variant},
+    Id,
+};
+use time::{Duration, OffsetDateTime};
+
+use crate::{routes::*, tests::router, Auth};
+
+#[tokio::test]
+async fn counts() {
+    let (state, route) = router();
+    let mut account: Account = acc_exp!(DCK, Maintain);
+    let (token, _) = account.login("shanlilinghuo").unwrap();
+    let id = account.id();
+    state.worlds.account.insert(account).await.unwrap();
+    let mut another: Account = acc_exp!(MYG, ReviewPost);
+    let (another_token, _) = another.login("123456").unwrap();
+    let another_id = another.id();
+    state.worlds.account.insert(another).await.unwrap();
+
+    let res = req!(route, GET => GET_STATS,
+        Auth { account: another_id, token: another_token }
+    );
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    let today = OffsetDateTime::now_utc().date();
+    let new_post = || {
+        Post::new(
+            "Club".to_owned(),
+            String::new(),
+            today..=(today + Duration::DAY),
+            Box::new([]),
+            id,
+            false,
+            Priority::Normal,
+            Post::MAX_DUR,
+        )
+        .unwrap()
+    };
+    let mut approved = new_post();
+    approved
+        .pust_state(State::new(Status::Approved, another_id, String::new()))
+        .unwrap();
+    state.worlds.post.insert(approved).await.unwrap();
+    state.worlds.post.insert(new_post()).await.unwrap();
+
+    // resources without payloads are hashed `0`, so they share a file
+    let video = || Resource::new(Variant::Video { duration: 60 }, Id(id));
+    let text = Resource::new(
+        Variant::Text {
+            title: "Club".to_owned(),
+            body: String::new(),
+            style: Default::default(),
+            duration: 15,
+        },
+        Id(id),
+    );
+    let file_name = text.file_name();
+    for resource in [video(), video(), text] {
+        state.worlds.resource.insert(resource).await.unwrap();
+    }
+    state
+        .resource_store
+        .put_bytes(&file_name, vec![0; 10].into())
+        .await
+        .unwrap();
+
+    let now = OffsetDateTime::now_utc();
+    for time in [now - Duration::HOUR, now + Duration::DAY] {
+        state
+            .worlds
+            .notification
+            .insert(Notification::new(
+                "Notice".to_owned(),
+                String::new(),
+                time,
+                id,
+            ))
+            .await
+            .unwrap();
+    }
+
+    let res = req!(route, GET => GET_STATS,
+        Auth { account: id, token: token.clone() }
+    );
+    assert!(res.status().is_success());
+    let res: serde_json::Value = p_json!(res);
+    let posts = &res["posts"];
+    assert_eq!(posts["total"], 2);
+    assert_eq!(posts["by_status"]["Approved"], 1);
+    assert_eq!(posts["by_status"]["Pending"], 1);
+    assert_eq!(posts["by_priority"]["Normal"], 2);
+    assert_eq!(posts["by_creator"][id.to_string()], 2);
+    assert_eq!(posts["by_department"]["SubIT"], 2);
+    assert_eq!(posts["by_department"]["击剑批"], 2);
+    assert!(posts["avg_review_turnaround"].as_i64().unwrap() >= 0);
+    assert_eq!(res["resources"]["Video"]["count"], 2);
+    assert_eq!(res["resources"]["Video"]["bytes"], 10);
+    assert_eq!(res["resources"]["Text"]["count"], 1);
+    assert_eq!(res["resources"]["Text"]["bytes"], 0);
+    assert_eq!(res["active_notifications"], 1);
+
+    // posts created out of the date range are not counted
+    let res = req!(route, GET => format!("/stats?before={}", today - Duration::DAY),
+        Auth { account: id, token }
+    );
+    assert!(res.status().is_success());
+    let res: serde_json::Value = p_json!(res);
+    assert_eq!(res["posts"]["total"], 0);
+    assert!(res["posts"]["avg_review_turnaround"].is_null());
+}
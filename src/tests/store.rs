@@ -0,0 +1,149 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{Request, State},
+    http::{header, Method, StatusCode},
+    response::{IntoResponse, Response},
+    Router,
+};
+use futures::StreamExt;
+use sms4_backend::resource::store::{
+    s3::{Config, S3},
+    ByteStream, ResourceStore,
+};
+use tokio::net::TcpListener;
+
+type Objects = Arc<Mutex<HashMap<String, Bytes>>>;
+
+/// A minimal S3-compatible server keeping objects in memory.
+async fn mock_s3(State(objects): State<Objects>, req: Request) -> Response {
+    let auth = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    if !auth.starts_with("AWS4-HMAC-SHA256 Credential=test/")
+        || !auth.contains("SignedHeaders=")
+        || !req.headers().contains_key("x-amz-date")
+    {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+
+    let path = req.uri().path().to_owned();
+    let Some(key) = path.strip_prefix("/bucket") else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let key = key.trim_start_matches('/').to_owned();
+    let method = req.method().clone();
+    let range = req
+        .headers()
+        .get(header::RANGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("bytes="))
+        .and_then(|v| v.split_once('-'))
+        .map(|(s, e)| (s.parse::<usize>().unwrap(), e.parse::<usize>().unwrap()));
+    let body = axum::body::to_bytes(req.into_body(), usize::MAX)
+        .await
+        .unwrap();
+
+    let mut objects = objects.lock().unwrap();
+    match method {
+        Method::PUT => {
+            objects.insert(key, body);
+            StatusCode::OK.into_response()
+        }
+        Method::GET if key.is_empty() => {
+            let contents: String = objects
+                .iter()
+                .map(|(k, v)| {
+                    format!(
+                        "<Contents><Key>{k}</Key><LastModified>2024-01-01T00:00:00.000Z</LastModified><Size>{}</Size></Contents>",
+                        v.len()
+                    )
+                })
+                .collect();
+            format!(
+                "<ListBucketResult><IsTruncated>false</IsTruncated>{contents}</ListBucketResult>"
+            )
+            .into_response()
+        }
+        Method::GET | Method::HEAD => match (objects.get(&key), range) {
+            (None, _) => StatusCode::NOT_FOUND.into_response(),
+            (Some(object), None) => Response::builder()
+                .header(header::CONTENT_LENGTH, object.len())
+                .header(header::LAST_MODIFIED, "Mon, 01 Jan 2024 00:00:00 GMT")
+                .body(Body::from(object.clone()))
+                .unwrap(),
+            (Some(object), Some((start, end))) => {
+                (StatusCode::PARTIAL_CONTENT, object.slice(start..=end)).into_response()
+            }
+        },
+        Method::DELETE => {
+            objects.remove(&key);
+            StatusCode::NO_CONTENT.into_response()
+        }
+        _ => StatusCode::METHOD_NOT_ALLOWED.into_response(),
+    }
+}
+
+async fn collect(stream: ByteStream) -> Vec<u8> {
+    stream
+        .fold(vec![], |mut buf, chunk| async move {
+            buf.extend_from_slice(&chunk.unwrap());
+            buf
+        })
+        .await
+}
+
+#[tokio::test]
+async fn s3_store() {
+    let objects = Objects::default();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let app = Router::new().fallback(mock_s3).with_state(objects.clone());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let store = S3::new(Config {
+        endpoint: format!("http://{addr}/"),
+        bucket: "bucket".to_owned(),
+        region: "us-east-1".to_owned(),
+        access_key: "test".to_owned(),
+        secret_key: "secret".to_owned(),
+        prefix: "res/".to_owned(),
+    });
+
+    store
+        .put_bytes("r_1", Bytes::from_static(b"hello, world"))
+        .await
+        .unwrap();
+    assert!(objects.lock().unwrap().contains_key("res/r_1"));
+
+    assert_eq!(
+        collect(store.get("r_1").await.unwrap()).await,
+        b"hello, world"
+    );
+    assert_eq!(
+        collect(store.get_range("r_1", 7..=11).await.unwrap()).await,
+        b"world"
+    );
+    let meta = store.head("r_1").await.unwrap();
+    assert_eq!(meta.len, 12);
+    assert!(meta.modified.is_some());
+
+    let list = store.list().await.unwrap();
+    assert_eq!(list.len(), 1);
+    assert_eq!(list[0].name, "r_1");
+    assert_eq!(list[0].len, 12);
+
+    store.delete("r_1").await.unwrap();
+    assert_eq!(
+        store.head("r_1").await.unwrap_err().kind(),
+        std::io::ErrorKind::NotFound
+    );
+    // deleting missing files is not an error
+    store.delete("r_1").await.unwrap();
+}